@@ -0,0 +1,362 @@
+use crate::{
+    utils::*,
+    traits::*,
+    consts::*,
+    shots::*,
+    dungeon::BlockTag,
+    pathfinding::MovementLayer,
+};
+use glam::f32::Vec2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AiState {
+    Idle,
+    Alert,
+    Pursue,
+    Attack,
+    Retreat,
+    Search,
+}
+
+/// Per enemy type tuning of the state machine.
+/// A retreat range of 0 keeps a brawler from ever retreating. Pursuit is switched by `can_pursue`, enemies that can't pursue stay alert until the target comes into attack range.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AiConfig {
+    pub sight_range: f32,
+    pub attack_range: f32,
+    pub retreat_range: f32,
+    pub alert_time: f32,
+    pub search_time: f32,
+    pub can_pursue: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Brain {
+    pub state: AiState,
+    pub config: AiConfig,
+    pub timer: f32,
+    pub last_known_pos: Option<Vec2>,
+}
+
+impl Brain {
+    pub fn new(config: AiConfig) -> Self {
+        Self {
+            state: AiState::Idle,
+            config,
+            timer: 0.,
+            last_known_pos: None,
+        }
+    }
+
+    /// Counts down the alert and search timers, called from `Actor::update`.
+    ///
+    pub fn tick(&mut self, delta_time: f32) {
+        self.timer = f32::max(0., self.timer - delta_time);
+    }
+
+    /// Runs one transition of the state machine and returns the new state.
+    ///
+    pub fn think(&mut self, pos: Vec2, target: Vec2, obstacles: &[Box<dyn Stationary>], sw: f32, sh: f32) -> AiState {
+        let distance = pos.distance(target);
+        let sees = distance <= self.config.sight_range && has_line_of_sight(pos, target, obstacles, sw, sh);
+
+        if sees { self.last_known_pos = Some(target); }
+
+        self.state = match self.state {
+            AiState::Idle if sees => {
+                self.timer = self.config.alert_time;
+                AiState::Alert
+            },
+            AiState::Idle => AiState::Idle,
+            AiState::Alert if self.timer > 0. => AiState::Alert,
+            AiState::Search if sees => self.engage(distance),
+            AiState::Search if self.timer == 0. || self.last_known_pos.is_none() => {
+                self.last_known_pos = None;
+                AiState::Idle
+            },
+            AiState::Search => AiState::Search,
+            _ if sees => self.engage(distance),
+            _ => {
+                self.timer = self.config.search_time;
+                AiState::Search
+            },
+        };
+
+        self.state
+    }
+
+    /// Where a moving enemy should head to in the current state, `None` means standing still.
+    ///
+    pub fn destination(&self, pos: Vec2, target: Vec2) -> Option<Vec2> {
+        match self.state {
+            AiState::Pursue | AiState::Attack if self.config.can_pursue => Some(target),
+            AiState::Retreat => Some(pos + (pos - target).normalize_or_zero()),
+            AiState::Search => self.last_known_pos,
+            _ => None,
+        }
+    }
+
+    fn engage(&self, distance: f32) -> AiState {
+        if distance < self.config.retreat_range { AiState::Retreat }
+        else if distance <= self.config.attack_range { AiState::Attack }
+        else if self.config.can_pursue { AiState::Pursue }
+        else { AiState::Alert }
+    }
+}
+
+/// Per enemy type tuning of dodging.
+/// Every dodge costs one point of agility, which slowly recharges.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvasionConfig {
+    pub agility: f32,
+    pub recharge: f32,
+    pub look_ahead: f32,
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evasion {
+    pub config: EvasionConfig,
+    pub agility: f32,
+    pub timer: f32,
+    pub dir: Vec2,
+}
+
+impl Evasion {
+    pub fn new(config: EvasionConfig) -> Self {
+        Self {
+            config,
+            agility: config.agility,
+            timer: 0.,
+            dir: Vec2::ZERO,
+        }
+    }
+
+    pub fn tick(&mut self, delta_time: f32) {
+        self.timer = f32::max(0., self.timer - delta_time);
+        self.agility = f32::min(self.config.agility, self.agility + self.config.recharge * delta_time);
+    }
+
+    pub fn is_dodging(&self) -> bool { self.timer > 0. }
+
+    /// Keeps sidestepping while a dodge lasts, otherwise starts a new one if a player shot is about to hit.
+    /// Returns the direction to move in while dodging.
+    ///
+    pub fn dodge(&mut self, pos: Vec2, radius: f32, shots: &[Shot], sw: f32, sh: f32) -> Option<Vec2> {
+        if self.is_dodging() { return Some(self.dir); }
+        if self.agility < 1. { return None; }
+
+        let dir = Evasion::threat(pos, radius, shots, self.config.look_ahead, sw, sh)?;
+        self.agility -= 1.;
+        self.timer = self.config.duration;
+        self.dir = dir;
+
+        Some(dir)
+    }
+
+    /// Finds the player shot that will hit the circle soonest within `look_ahead` seconds.
+    /// Returns the direction that leads off its path, perpendicular to the shot.
+    ///
+    pub fn threat(pos: Vec2, radius: f32, shots: &[Shot], look_ahead: f32, sw: f32, sh: f32) -> Option<Vec2> {
+        shots.iter()
+            .filter(|s| s.tag == ShotTag::Player)
+            .filter_map(|s| {
+                let velocity = match s.get_velocity() == Vec2::ZERO {
+                    true => s.get_translation() * s.speed,
+                    false => s.get_velocity(),
+                };
+                if velocity == Vec2::ZERO { return None; }
+
+                let offset = pos - s.get_pos();
+                let t = offset.dot(velocity) / velocity.length_squared();
+                if t < 0. || t > look_ahead * DESIRED_FPS as f32 { return None; }
+
+                let miss = offset - velocity * t;
+                if miss.length() > radius + s.get_bcircle(sw, sh).1 { return None; }
+
+                let dir = match miss.length() < 1e-3 {
+                    true => velocity.perp(),
+                    false => miss,
+                };
+                Some((t, dir.normalize()))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, dir)| dir)
+    }
+}
+
+/// Per enemy type tuning of charging.
+/// `dash_speed` multiplies the speed of the enemy, `tolerance` is how far off the row or column the player may be, in tiles.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChargeConfig {
+    pub windup: f32,
+    pub dash_speed: f32,
+    pub stun: f32,
+    pub tolerance: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChargePhase {
+    Patrol,
+    Windup {
+        dir: Vec2,
+        timer: f32,
+    },
+    Dash {
+        dir: Vec2,
+    },
+    Stunned {
+        timer: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Charge {
+    pub config: ChargeConfig,
+    pub phase: ChargePhase,
+}
+
+impl Charge {
+    pub fn new(config: ChargeConfig) -> Self {
+        Self {
+            config,
+            phase: ChargePhase::Patrol,
+        }
+    }
+
+    pub fn tick(&mut self, delta_time: f32) {
+        match &mut self.phase {
+            ChargePhase::Windup { timer, .. } | ChargePhase::Stunned { timer } => *timer = f32::max(0., *timer - delta_time),
+            _ => (),
+        }
+    }
+
+    pub fn is_dashing(&self) -> bool { matches!(self.phase, ChargePhase::Dash { .. }) }
+
+    pub fn is_stunned(&self) -> bool { matches!(self.phase, ChargePhase::Stunned { .. }) }
+
+    /// Direction the charger faces while it winds up and dashes.
+    ///
+    pub fn dir(&self) -> Option<Vec2> {
+        match self.phase {
+            ChargePhase::Windup { dir, .. } | ChargePhase::Dash { dir } => Some(dir),
+            _ => None,
+        }
+    }
+
+    /// Runs one step of the charge and returns the direction to move in, `None` means the charger is free to patrol.
+    /// A dash ends in a stun as soon as anything standing on the floor is right in front of the charger.
+    ///
+    pub fn charge(&mut self, pos: Vec2, target: Vec2, radius: f32, obstacles: &[Box<dyn Stationary>], sw: f32, sh: f32) -> Option<Vec2> {
+        self.phase = match self.phase {
+            ChargePhase::Patrol => match Charge::lined_up(pos, target, self.config.tolerance, obstacles, sw, sh) {
+                Some(dir) => ChargePhase::Windup { dir, timer: self.config.windup },
+                None => return None,
+            },
+            ChargePhase::Windup { dir, timer: 0. } => ChargePhase::Dash { dir },
+            ChargePhase::Dash { dir } if Charge::hits_wall(pos, dir, radius, obstacles, sw, sh) => ChargePhase::Stunned { timer: self.config.stun },
+            ChargePhase::Stunned { timer: 0. } => {
+                self.phase = ChargePhase::Patrol;
+                return None;
+            },
+            phase => phase,
+        };
+
+        match self.phase {
+            ChargePhase::Dash { dir } => Some(dir),
+            _ => Some(Vec2::ZERO),
+        }
+    }
+
+    /// Direction towards the target if it stands on the same row or column with nothing in between.
+    ///
+    pub fn lined_up(pos: Vec2, target: Vec2, tolerance: f32, obstacles: &[Box<dyn Stationary>], sw: f32, sh: f32) -> Option<Vec2> {
+        let offset = target - pos;
+        let (tw, th) = (sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32);
+
+        let dir = if offset.y.abs() <= th * tolerance { Vec2::new(offset.x.signum(), 0.) }
+            else if offset.x.abs() <= tw * tolerance { Vec2::new(0., offset.y.signum()) }
+            else { return None; };
+
+        match has_line_of_sight(pos, target, obstacles, sw, sh) {
+            true => Some(dir),
+            false => None,
+        }
+    }
+
+    fn hits_wall(pos: Vec2, dir: Vec2, radius: f32, obstacles: &[Box<dyn Stationary>], sw: f32, sh: f32) -> bool {
+        let (mut cp, mut cn) = (Vec2::ZERO, Vec2::ZERO);
+        let mut ct = 0.;
+        let reach = dir * (radius + f32::min(sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32) * 0.2);
+
+        obstacles.iter()
+            .filter(|o| MovementLayer::Ground.is_blocked_by(&o.get_tag()))
+            .any(|o| ray_vs_rect(&pos, &reach, &o.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct) && ct < 1.)
+    }
+}
+
+/// Per enemy type tuning of burrowing, how long each phase lasts in seconds.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurrowConfig {
+    pub underground: f32,
+    pub telegraph: f32,
+    pub surfaced: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BurrowPhase {
+    Underground,
+    Surfacing,
+    Surfaced,
+}
+
+/// Cycle of a burrowing enemy: it digs towards the player, telegraphs where it comes up, then fights on the surface.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Burrow {
+    pub config: BurrowConfig,
+    pub phase: BurrowPhase,
+    pub timer: f32,
+}
+
+impl Burrow {
+    pub fn new(config: BurrowConfig) -> Self {
+        Self {
+            config,
+            phase: BurrowPhase::Underground,
+            timer: config.underground,
+        }
+    }
+
+    /// Counts down the current phase and moves on to the next one when it runs out.
+    ///
+    pub fn tick(&mut self, delta_time: f32) {
+        self.timer = f32::max(0., self.timer - delta_time);
+        if self.timer > 0. { return; }
+
+        (self.phase, self.timer) = match self.phase {
+            BurrowPhase::Underground => (BurrowPhase::Surfacing, self.config.telegraph),
+            BurrowPhase::Surfacing => (BurrowPhase::Surfaced, self.config.surfaced),
+            BurrowPhase::Surfaced => (BurrowPhase::Underground, self.config.underground),
+        };
+    }
+
+    pub fn is_underground(&self) -> bool { self.phase == BurrowPhase::Underground }
+
+    pub fn is_surfaced(&self) -> bool { self.phase == BurrowPhase::Surfaced }
+}
+
+/// Sight is blocked by everything standing on the floor, a ray is cast to the target against every bounding box.
+///
+pub fn has_line_of_sight(from: Vec2, to: Vec2, obstacles: &[Box<dyn Stationary>], sw: f32, sh: f32) -> bool {
+    let (mut cp, mut cn) = (Vec2::ZERO, Vec2::ZERO);
+    let mut ct = 0.;
+
+    !obstacles.iter()
+        .filter(|o| !o.get_tag().is_floor() && !matches!(o.get_tag(), BlockTag::Hatch(_)))
+        .any(|o| ray_vs_rect(&from, &(to - from), &o.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct) && ct < 1.)
+}
@@ -0,0 +1,69 @@
+use glam::f32::{Vec2, Mat2};
+use rand::{thread_rng, Rng};
+
+/// How well a shooter aims.
+/// `lead` is the fraction of the player's movement that is led, `spread` the largest random error in radians.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accuracy {
+    pub lead: f32,
+    pub spread: f32,
+}
+
+impl Accuracy {
+    /// Scales the accuracy of an enemy type by the accuracy of the floor.
+    ///
+    pub fn scaled(&self, by: &Accuracy) -> Accuracy {
+        Accuracy {
+            lead: (self.lead * by.lead).clamp(0., 1.),
+            spread: self.spread * by.spread,
+        }
+    }
+}
+
+/// Point where a shot fired now with the given speed meets a target moving with constant velocity.
+/// Velocities are in pixels per frame, like the rest of the actors.
+/// Returns `None` if the target is too fast to be caught.
+///
+pub fn intercept(shooter: Vec2, target: Vec2, target_velocity: Vec2, shot_speed: f32) -> Option<Vec2> {
+    let offset = target - shooter;
+    let a = target_velocity.length_squared() - shot_speed * shot_speed;
+    let b = 2. * offset.dot(target_velocity);
+    let c = offset.length_squared();
+
+    let t = if a.abs() < 1e-6 {
+        if b.abs() < 1e-6 { return None; }
+        -c / b
+    }
+    else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. { return None; }
+
+        let sqrt = discriminant.sqrt();
+        let (t1, t2) = ((-b - sqrt) / (2. * a), (-b + sqrt) / (2. * a));
+        match (t1 > 0., t2 > 0.) {
+            (true, true) => f32::min(t1, t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return None,
+        }
+    };
+
+    if t <= 0. { return None; }
+
+    Some(target + target_velocity * t)
+}
+
+/// Direction to shoot in, led towards the intercept point by the accuracy and randomly spread.
+///
+pub fn aim(shooter: Vec2, target: Vec2, target_velocity: Vec2, shot_speed: f32, accuracy: &Accuracy) -> Vec2 {
+    let lead_point = match intercept(shooter, target, target_velocity, shot_speed) {
+        Some(point) => target + (point - target) * accuracy.lead,
+        None => target,
+    };
+    let dir = (lead_point - shooter).normalize_or_zero();
+
+    if accuracy.spread <= 0. { return dir; }
+
+    Mat2::from_angle(thread_rng().gen_range(-accuracy.spread..=accuracy.spread)) * dir
+}
@@ -0,0 +1,379 @@
+use ggez::{
+    Context,
+    GameResult,
+    GameError,
+    filesystem,
+};
+use std::{
+    collections::HashMap,
+    io::Read,
+    fs,
+    path::Path,
+};
+use crate::{
+    assets::Assets,
+    consts::*,
+    enemies::*,
+    ai::*,
+    aiming::Accuracy,
+    pathfinding::MovementLayer,
+    utils::Errors,
+};
+use glam::f32::Vec2;
+
+/// Smallest value a number of a definition accepts, every number has to be finite.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bound {
+    Any,
+    NonNegative,
+    Positive,
+}
+
+/// Enemy archetypes by layout character, loaded from the definitions in `resources/enemies`.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Bestiary {
+    pub enemies: HashMap<char, Enemy>,
+}
+
+impl Bestiary {
+    /// Loads every definition through the game filesystem and checks that the sprites and sounds it names exist.
+    ///
+    pub fn new(ctx: &mut Context, assets: &Assets) -> GameResult<Self> {
+        let mut sources = Vec::new();
+
+        for f in filesystem::read_dir(ctx, "/enemies")? {
+            let mut source = String::new();
+            filesystem::open(ctx, &f)?.read_to_string(&mut source)?;
+            sources.push((f.file_name().unwrap().to_str().unwrap().to_owned(), source));
+        }
+
+        let bestiary = Bestiary::from_sources(&sources).map_err(Into::<GameError>::into)?;
+
+        for (c, enemy) in bestiary.enemies.iter() {
+            if let Some(sprite) = Bestiary::sprites(&enemy.look).into_iter().find(|s| !assets.sprites.contains_key(s)) {
+                return Err(Errors::EnemyParse(format!("enemy '{}': unknown sprite `{}`", c, sprite)).into());
+            }
+            if !assets.audio.contains_key(&enemy.death_sound) {
+                return Err(Errors::EnemyParse(format!("enemy '{}': unknown sound `{}`", c, enemy.death_sound)).into());
+            }
+        }
+
+        Ok(bestiary)
+    }
+
+    /// Loads the definitions straight from a directory on disk, without checking the assets.
+    ///
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, Errors> {
+        let mut sources = Vec::new();
+
+        let entries = fs::read_dir(dir.as_ref()).map_err(|e| Errors::EnemyParse(format!("{}: {}", dir.as_ref().display(), e)))?;
+        for entry in entries.flatten() {
+            let source = fs::read_to_string(entry.path()).map_err(|e| Errors::EnemyParse(format!("{}: {}", entry.path().display(), e)))?;
+            sources.push((entry.file_name().to_string_lossy().into_owned(), source));
+        }
+
+        Bestiary::from_sources(&sources)
+    }
+
+    /// Parses `(file name, contents)` pairs, two definitions can't share a layout character.
+    /// Spawners get their minion from the other definitions, a minion can't be a spawner itself.
+    ///
+    pub fn from_sources(sources: &[(String, String)]) -> Result<Self, Errors> {
+        let mut enemies = HashMap::new();
+        let mut files: HashMap<char, &str> = HashMap::new();
+
+        for (file, source) in sources {
+            let (c, enemy) = Bestiary::parse(source).map_err(|e| Errors::EnemyParse(format!("{}: {}", file, e)))?;
+
+            if let Some(other) = files.insert(c, file) {
+                return Err(Errors::EnemyParse(format!("{}: layout character '{}' is already used by {}", file, c, other)));
+            }
+            enemies.insert(c, enemy);
+        }
+
+        let templates = enemies.clone();
+        for (c, enemy) in enemies.iter_mut() {
+            let spawner = match &mut enemy.spawner {
+                Some(spawner) => spawner,
+                None => continue,
+            };
+            let template = match templates.get(&spawner.minion) {
+                Some(template) if template.spawner.is_some() => return Err(Errors::EnemyParse(format!("{}: minion '{}' spawns minions itself", files[c], spawner.minion))),
+                Some(template) => template,
+                None => return Err(Errors::EnemyParse(format!("{}: unknown minion '{}'", files[c], spawner.minion))),
+            };
+            spawner.template = Some(Box::new(template.clone()));
+        }
+
+        Ok(Self { enemies })
+    }
+
+    pub fn get(&self, c: char) -> Option<&Enemy> { self.enemies.get(&c) }
+
+    pub fn contains(&self, c: char) -> bool { self.enemies.contains_key(&c) }
+
+    /// Parses a single definition made of `key = value` lines, lines starting with `#` are comments.
+    /// Errors are prefixed with the line number they occur on.
+    ///
+    pub fn parse(source: &str) -> Result<(char, Enemy), String> {
+        let mut enemy = Enemy::default();
+        let mut c = None;
+        let mut sprite = None;
+        let (mut cardinals, mut diagonals) = (None, None);
+        let mut look = None;
+        let (mut minion, mut spawning) = (None, None);
+
+        for (n, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+
+            let err = |reason: String| format!("line {}: {}", n + 1, reason);
+            let (key, value) = line.split_once('=').ok_or_else(|| err(format!("expected `key = value`, found `{}`", line)))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            let number = |v: &str, bound: Bound| Bestiary::number(key, v, bound).map_err(err);
+            let count = |v: &str| Bestiary::count(key, v).map_err(err);
+            let flag = |v: &str| Bestiary::flag(key, v).map_err(err);
+            let unknown = || err(format!("unknown {} `{}`", key, value));
+
+            match key {
+                "char" => {
+                    let mut chars = value.chars();
+                    c = match (chars.next(), chars.next()) {
+                        (Some(ch), None) if LAYOUT_RESERVED_CHARS.contains(ch) => return Err(err(format!("layout character '{}' is reserved for tiles", ch))),
+                        (Some(ch), None) => Some(ch),
+                        _ => return Err(err(format!("`char` expects a single character, found `{}`", value))),
+                    };
+                },
+                "look" => look = Some(value.to_string()),
+                "sprite" => sprite = Some(value.to_string()),
+                "sprite_shoot_cardinals" => cardinals = Some(value.to_string()),
+                "sprite_shoot_diagonals" => diagonals = Some(value.to_string()),
+                "death_sound" => enemy.death_sound = value.to_string(),
+                "health" => {
+                    enemy.health = number(value, Bound::Positive)?;
+                    enemy.max_health = enemy.health;
+                },
+                "speed" => enemy.speed = number(value, Bound::NonNegative)?,
+                "acceleration" => enemy.acceleration = number(value, Bound::NonNegative)?,
+                "decay" => enemy.decay = number(value, Bound::NonNegative)?,
+                "damage" => enemy.damage = number(value, Bound::NonNegative)?,
+                "shoot_rate" => enemy.shoot_rate = number(value, Bound::Positive)?,
+                "shoot_range" => enemy.shoot_range = number(value, Bound::Positive)?,
+                "scale" => {
+                    let scale = value.split_whitespace().map(|v| number(v, Bound::Positive)).collect::<Result<Vec<_>, _>>()?;
+                    enemy.props.scale = match scale.as_slice() {
+                        [s] => Vec2::splat(*s),
+                        [x, y] => Vec2::new(*x, *y),
+                        _ => return Err(err(format!("`scale` expects one or two numbers, found `{}`", value))),
+                    };
+                },
+                "accuracy" => {
+                    let [lead, spread] = Bestiary::fields(key, value, "a lead and a spread").map_err(err)?;
+                    enemy.accuracy = Accuracy { lead: number(lead, Bound::NonNegative)?, spread: number(spread, Bound::NonNegative)? };
+                },
+                "movement" => enemy.movement = match value {
+                    "still" => Movement::Still,
+                    "chase" => Movement::Chase,
+                    "wander" => Movement::Wander,
+                    "flock" => Movement::Flock(FLOCK_FLY),
+                    _ => return Err(unknown()),
+                },
+                "charge" => {
+                    let [windup, dash_speed, stun, tolerance] = Bestiary::fields(key, value, "a windup, a dash speed, a stun and a tolerance").map_err(err)?;
+                    enemy.movement = Movement::Charge(Charge::new(ChargeConfig {
+                        windup: number(windup, Bound::NonNegative)?,
+                        dash_speed: number(dash_speed, Bound::Positive)?,
+                        stun: number(stun, Bound::NonNegative)?,
+                        tolerance: number(tolerance, Bound::NonNegative)?,
+                    }));
+                },
+                "burrow" => {
+                    let [underground, telegraph, surfaced] = Bestiary::fields(key, value, "how long it stays underground, telegraphs and stays surfaced").map_err(err)?;
+                    enemy.movement = Movement::Burrow(Burrow::new(BurrowConfig {
+                        underground: number(underground, Bound::Positive)?,
+                        telegraph: number(telegraph, Bound::NonNegative)?,
+                        surfaced: number(surfaced, Bound::Positive)?,
+                    }));
+                },
+                "attack" => enemy.attack = match value {
+                    "none" => Attack::None,
+                    "aimed" => Attack::Aimed,
+                    "cross" => Attack::Cross,
+                    _ => return Err(unknown()),
+                },
+                "on_hit" => enemy.on_hit = match value {
+                    "flash" => OnHit::Flash,
+                    "ignore" => OnHit::Ignore,
+                    _ => return Err(unknown()),
+                },
+                "on_death" => enemy.death = match value {
+                    "vanish" => OnDeath::Vanish,
+                    "puddle" => OnDeath::Puddle,
+                    _ => return Err(unknown()),
+                },
+                "split" => {
+                    let [copies, scale, speed, health, min_scale] = Bestiary::fields(key, value, "a copy count, a scale, a speed, a health and a minimum scale").map_err(err)?;
+                    let config = SplitConfig {
+                        count: count(copies)?,
+                        scale: number(scale, Bound::Positive)?,
+                        speed: number(speed, Bound::Positive)?,
+                        health: number(health, Bound::Positive)?,
+                        min_scale: number(min_scale, Bound::Positive)?,
+                    };
+                    if config.scale >= 1. {
+                        return Err(err(format!("`split` needs a scale below 1 for the copies to stop splitting, found `{}`", scale)));
+                    }
+                    enemy.death = OnDeath::Split(config);
+                },
+                "layer" => enemy.layer = match value {
+                    "ground" => MovementLayer::Ground,
+                    "flying" => MovementLayer::Flying,
+                    "phasing" => MovementLayer::Phasing,
+                    _ => return Err(unknown()),
+                },
+                "brain" => enemy.brain = match value {
+                    "none" => None,
+                    "mask" => Some(Brain::new(AI_MASK)),
+                    "blue_guy" => Some(Brain::new(AI_BLUE_GUY)),
+                    "slime" => Some(Brain::new(AI_SLIME)),
+                    _ => return Err(unknown()),
+                },
+                "evasion" => enemy.evasion = match value {
+                    "none" => None,
+                    "blue_guy" => Some(Evasion::new(EVASION_BLUE_GUY)),
+                    "boss" => Some(Evasion::new(EVASION_BOSS)),
+                    _ => return Err(unknown()),
+                },
+                "volley" => {
+                    let [shots, spread, spin] = Bestiary::fields(key, value, "a shot count and two angles").map_err(err)?;
+                    enemy.attack = Attack::Volley(VolleyConfig {
+                        count: count(shots)?,
+                        spread: number(spread, Bound::NonNegative)?,
+                        spin: number(spin, Bound::Any)?,
+                    });
+                },
+                "creep" => enemy.leaves_creep = flag(value)?,
+                "boss" => enemy.is_boss = flag(value)?,
+                "blocks_clear" => enemy.blocks_clear = flag(value)?,
+                "embedded" => enemy.embedded = flag(value)?,
+                "minion" => {
+                    let mut chars = value.chars();
+                    minion = match (chars.next(), chars.next()) {
+                        (Some(ch), None) => Some(ch),
+                        _ => return Err(err(format!("`minion` expects a single character, found `{}`", value))),
+                    };
+                },
+                "spawn" => {
+                    let [interval, cap, minions_die] = Bestiary::fields(key, value, "an interval, a cap and true or false").map_err(err)?;
+                    spawning = Some(SpawnerConfig {
+                        interval: number(interval, Bound::Positive)?,
+                        cap: count(cap)?,
+                        minions_die: flag(minions_die)?,
+                    });
+                },
+                "worm" => {
+                    let [segments, spacing, health] = Bestiary::fields(key, value, "a segment count, a spacing and shared or split").map_err(err)?;
+                    enemy.worm = Some(WormConfig {
+                        segments: count(segments)?,
+                        spacing: number(spacing, Bound::Positive)?,
+                        shared: match health {
+                            "shared" => true,
+                            "split" => false,
+                            _ => return Err(err(format!("`worm` expects shared or split health, found `{}`", health))),
+                        },
+                    });
+                },
+                "orbit" => {
+                    let [blockers, distance, speed, health] = Bestiary::fields(key, value, "a blocker count, a distance, a speed and a health").map_err(err)?;
+                    enemy.orbit = Some(Orbit::new(OrbitConfig {
+                        count: count(blockers)?,
+                        distance: number(distance, Bound::Positive)?,
+                        speed: number(speed, Bound::Any)?,
+                        health: number(health, Bound::Positive)?,
+                    }));
+                },
+                "anchored" => enemy.anchored = flag(value)?,
+                _ => return Err(err(format!("unknown key `{}`", key))),
+            }
+        }
+
+        let c = c.ok_or("missing `char`")?;
+        if enemy.on_hit == OnHit::Ignore && enemy.blocks_clear {
+            return Err("invulnerable enemies can't block the room from being cleared, set `blocks_clear = false`".to_string());
+        }
+        enemy.spawner = match (minion, spawning) {
+            (Some(minion), config) => Some(Spawner::new(minion, config.unwrap_or(SPAWNER_NEST))),
+            (None, Some(_)) => return Err("`spawn` needs a `minion` to spawn".to_string()),
+            (None, None) => None,
+        };
+        let mut base = || sprite.take().ok_or("missing `sprite`");
+
+        enemy.look = match look.as_deref() {
+            Some("trembling") => Look::Trembling(base()?),
+            Some("rotating") => Look::Rotating(base()?),
+            Some("directional") => Look::Directional(base()?),
+            Some("shooting") => Look::Shooting {
+                base: base()?,
+                cardinals: cardinals.ok_or("missing `sprite_shoot_cardinals`")?,
+                diagonals: diagonals.ok_or("missing `sprite_shoot_diagonals`")?,
+            },
+            Some("fly") => Look::Fly,
+            Some("charger") => Look::Charger,
+            Some("turret") => Look::Turret,
+            Some("burrower") => Look::Burrower,
+            Some("nest") => Look::Nest,
+            Some("worm") => Look::Worm,
+            Some("warden") => Look::Warden,
+            Some(other) => return Err(format!("unknown look `{}`", other)),
+            None => return Err("missing `look`".to_string()),
+        };
+
+        Ok((c, enemy))
+    }
+
+    /// Parses a finite number and checks it against the bound of its key.
+    ///
+    fn number(key: &str, value: &str, bound: Bound) -> Result<f32, String> {
+        let number = value.parse::<f32>().ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("`{}` expects a number, found `{}`", key, value))?;
+
+        match bound {
+            Bound::NonNegative if number < 0. => Err(format!("`{}` can't be negative, found `{}`", key, value)),
+            Bound::Positive if number <= 0. => Err(format!("`{}` must be greater than 0, found `{}`", key, value)),
+            _ => Ok(number),
+        }
+    }
+
+    fn count(key: &str, value: &str) -> Result<usize, String> {
+        match value.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!("`{}` expects a count of at least 1, found `{}`", key, value)),
+        }
+    }
+
+    fn flag(key: &str, value: &str) -> Result<bool, String> {
+        value.parse::<bool>().map_err(|_| format!("`{}` expects true or false, found `{}`", key, value))
+    }
+
+    /// Splits a value into exactly `N` fields separated by spaces, `expects` describes them in the error.
+    ///
+    fn fields<'a, const N: usize>(key: &str, value: &'a str, expects: &str) -> Result<[&'a str; N], String> {
+        value.split_whitespace()
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| format!("`{}` expects {}, found `{}`", key, expects, value))
+    }
+
+    fn sprites(look: &Look) -> Vec<String> {
+        match look {
+            Look::Trembling(s) | Look::Rotating(s) => vec![s.clone()],
+            Look::Directional(prefix) => ["north", "east", "south", "west"].iter().map(|d| format!("{}_{}", prefix, d)).collect(),
+            Look::Shooting { base, cardinals, diagonals } => vec![base.clone(), cardinals.clone(), diagonals.clone()],
+            Look::Fly | Look::Charger | Look::Turret | Look::Burrower | Look::Nest | Look::Worm | Look::Warden => Vec::new(),
+        }
+    }
+}
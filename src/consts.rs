@@ -1,9 +1,17 @@
-use crate::items::{ItemPassive, ItemActive, ItemTag};
+use crate::{
+    items::{ItemPassive, ItemActive, ItemTag},
+    difficulty::Difficulty,
+    ai::{AiConfig, EvasionConfig},
+    flocking::FlockConfig,
+    enemies::SpawnerConfig,
+    aiming::Accuracy,
+};
 
 pub const DEFAULT_SCREEN_WIDTH: f32 = 1280.;
 pub const DEFAULT_SCREEN_HEIGHT: f32 = 720.;
 pub const DESIRED_FPS: u32 = 60;
 pub const MAX_LEVEL: u32 = 5;
+pub const BEST_DEPTH_FILE: &str = "/best_depth.txt";
 
 pub const TRANSITION_SCENE_COOLDOWN: f32 = 3.;
 
@@ -18,6 +26,12 @@ pub const ITEM_HOLDER_POS: (f32, f32) = (0.05, 0.05);
 pub const MINIMAP_SCALE: f32 = 0.2;
 pub const MINIMAP_POS: (f32, f32) = (1. - MINIMAP_SCALE, 0.);
 
+pub const MAP_ZOOM_MIN: f32 = 0.5;
+pub const MAP_ZOOM_MAX: f32 = 3.;
+pub const MAP_ZOOM_STEP: f32 = 0.25;
+
+pub const DEPTH_TEXT_POS: (f32, f32) = (1. - MINIMAP_SCALE / 2., MINIMAP_SCALE + 0.03);
+
 pub const PLAYER_SCALE: f32 = 0.6;
 pub const PLAYER_SHOOT_RATE: f32 = 2.5;
 pub const PLAYER_MAX_SHOOT_RATE: f32 = 10.;
@@ -40,32 +54,215 @@ pub const ENEMY_DAMAGE: f32 = 0.5;
 pub const ENEMY_AFTERLOCK_COOLDOWN: f32 = 2.;
 pub const ENEMY_WANDERER_CHANGE_DIRECTION_COOLDOWN: f32 = 2.;
 
+pub const AI_MASK: AiConfig = AiConfig {
+    sight_range: ENEMY_SHOOT_RANGE,
+    attack_range: ENEMY_SHOOT_RANGE * 0.8,
+    retreat_range: 0.,
+    alert_time: 0.5,
+    search_time: 1.,
+    can_pursue: false,
+};
+pub const AI_BLUE_GUY: AiConfig = AiConfig {
+    sight_range: 900.,
+    attack_range: 0.,
+    retreat_range: 0.,
+    alert_time: 0.3,
+    search_time: 3.,
+    can_pursue: true,
+};
+pub const AI_SLIME: AiConfig = AiConfig {
+    sight_range: 400.,
+    attack_range: 0.,
+    retreat_range: 150.,
+    alert_time: 0.2,
+    search_time: 0.,
+    can_pursue: false,
+};
+
+pub const EVASION_BLUE_GUY: EvasionConfig = EvasionConfig {
+    agility: 2.,
+    recharge: 0.5,
+    look_ahead: 0.6,
+    duration: 0.25,
+};
+pub const EVASION_BOSS: EvasionConfig = EvasionConfig {
+    agility: 1.,
+    recharge: 0.2,
+    look_ahead: 0.8,
+    duration: 0.4,
+};
+
+pub const SPAWNER_NEST: SpawnerConfig = SpawnerConfig {
+    interval: 3.,
+    cap: 4,
+    minions_die: true,
+};
+
+/// Radius of an orbiting blocker relative to the radius of the enemy it circles.
+///
+pub const ORBIT_BLOCKER_SIZE: f32 = 0.35;
+
+pub const FLOCK_FLY: FlockConfig = FlockConfig {
+    radius: 120.,
+    separation: 1.5,
+    alignment: 0.4,
+    cohesion: 0.6,
+    attraction: 1.,
+};
+
+pub const PATH_STRAIGHT_COST: u32 = 10;
+pub const PATH_DIAGONAL_COST: u32 = 14;
+
 pub const BOSS_HEALTH: f32 = 50.;
 
 pub const SHOT_SPEED: f32 = 6.;
 pub const SHOT_SCALE: f32 = 0.3;
 
+pub const SPIKES_EXTENDED_TIME: f32 = 1.5;
+pub const SPIKES_WARNING_TIME: f32 = 0.6;
+pub const SPIKES_RETRACTED_TIME: f32 = 2.;
+pub const SPIKES_PLAYER_DAMAGE: f32 = 1.;
+pub const SPIKES_ENEMY_DAMAGE: f32 = 3.;
+
+pub const FIRE_HEALTH: f32 = 4.;
+pub const FIRE_DAMAGE: f32 = 1.;
+pub const FIRE_ENEMY_DAMAGE: f32 = 2.;
+pub const FIRE_SHOOT_RATE: f32 = 0.5;
+pub const FIRE_SHOOT_RANGE: f32 = 400.;
+pub const FIRE_DROP_CHANCE: f64 = 0.3;
+pub const ROOM_DROP_CHANCE: f64 = 0.8;
+
+pub const SURFACE_ICE_MODIFIERS: (f32, f32, f32) = (1.2, 0.55, 0.35);
+pub const SURFACE_STICKY_MODIFIERS: (f32, f32, f32) = (0.5, 2., 0.6);
+pub const SURFACE_SPEED_PAD_MODIFIERS: (f32, f32, f32) = (1.6, 1., 1.5);
+
+pub const CREEP_SCALE: f32 = 0.7;
+pub const CREEP_LIFETIME: f32 = 3.;
+pub const CREEP_COOLDOWN: f32 = 0.3;
+pub const CREEP_PUKE_DAMAGE: f32 = 2.;
+pub const CREEP_PUKE_SLOW: f32 = 0.5;
+pub const CREEP_SLIME_DAMAGE: f32 = 0.5;
+pub const CREEP_SLIME_SLOW: f32 = 0.7;
+
 pub const COLLECTABLE_SCALE: f32 = 0.4;
 
 pub const ITEM_COOLDOWN: f32 = 3.;
 pub const ITEM_SCALE: f32 = 0.6;
 pub const ITEM_POOL_PASSIVE: &[ItemTag] = &[
     ItemTag::Passive(ItemPassive::IncreaseMaxHealth(1.)),
+    ItemTag::Passive(ItemPassive::PukeTrail),
+    ItemTag::Passive(ItemPassive::Compass),
+    ItemTag::Passive(ItemPassive::Map),
 ];
 pub const ITEM_POOL_ACTIVE: &[ItemTag] = &[
     ItemTag::Active(ItemActive::Heal(1.)),
 ];
 
 pub const ANIMATION_COOLDOWN: f32 = 0.5;
+pub const HAZARD_TICK: f32 = 0.5;
 
 pub const DUNGEON_GRID_ROWS: usize = 8;
 pub const DUNGEON_GRID_COLS: usize = 9;
+pub const DUNGEON_MAX_ROOMS: usize = 20;
+
+pub const ENDLESS_HEALTH_SCALE: f32 = 0.25;
+pub const ENDLESS_SPEED_SCALE: f32 = 0.05;
+pub const ENDLESS_SPEED_MAX: f32 = 1.5;
+pub const ENDLESS_SHOOT_RATE_SCALE: f32 = 0.15;
+pub const ENDLESS_CURSE_CHANCE: f64 = 0.15;
+pub const ENDLESS_CURSE_CHANCE_MAX: f64 = 0.6;
+pub const ENDLESS_EXTRA_ENEMIES: &[char] = &['m', 'b', 's'];
+pub const DARKNESS_RADIUS: f32 = 0.25;
+
+/// One entry per level up to `MAX_LEVEL`, the multipliers scale the base stats of the enemies.
+///
+pub const DIFFICULTY_CURVE: &[Difficulty] = &[
+    Difficulty {
+        room_count: (7, 8),
+        item_rooms: 1,
+        enemy_health: 1.,
+        enemy_damage: 2.,
+        enemy_speed: 1.,
+        enemy_shoot_rate: 1.,
+        enemy_accuracy: Accuracy { lead: 0., spread: 1.5 },
+        boss_health: 1.,
+        boss_damage: 2.,
+        boss_shoot_rate: 1.,
+        drop_rate: 1.,
+        extra_enemies: 0,
+        curse_chance: 0.,
+    },
+    Difficulty {
+        room_count: (9, 10),
+        item_rooms: 1,
+        enemy_health: 1.1,
+        enemy_damage: 3.,
+        enemy_speed: 1.,
+        enemy_shoot_rate: 1.05,
+        enemy_accuracy: Accuracy { lead: 0.25, spread: 1.25 },
+        boss_health: 1.1,
+        boss_damage: 3.,
+        boss_shoot_rate: 1.05,
+        drop_rate: 1.,
+        extra_enemies: 0,
+        curse_chance: 0.,
+    },
+    Difficulty {
+        room_count: (11, 12),
+        item_rooms: 1,
+        enemy_health: 1.2,
+        enemy_damage: 3.,
+        enemy_speed: 1.05,
+        enemy_shoot_rate: 1.1,
+        enemy_accuracy: Accuracy { lead: 0.5, spread: 1. },
+        boss_health: 1.2,
+        boss_damage: 3.,
+        boss_shoot_rate: 1.1,
+        drop_rate: 0.95,
+        extra_enemies: 0,
+        curse_chance: 0.,
+    },
+    Difficulty {
+        room_count: (13, 14),
+        item_rooms: 1,
+        enemy_health: 1.35,
+        enemy_damage: 3.,
+        enemy_speed: 1.1,
+        enemy_shoot_rate: 1.15,
+        enemy_accuracy: Accuracy { lead: 0.75, spread: 0.75 },
+        boss_health: 1.35,
+        boss_damage: 3.,
+        boss_shoot_rate: 1.2,
+        drop_rate: 0.9,
+        extra_enemies: 0,
+        curse_chance: 0.,
+    },
+    Difficulty {
+        room_count: (15, 16),
+        item_rooms: 1,
+        enemy_health: 1.5,
+        enemy_damage: 3.,
+        enemy_speed: 1.15,
+        enemy_shoot_rate: 1.2,
+        enemy_accuracy: Accuracy { lead: 1., spread: 0.5 },
+        boss_health: 1.5,
+        boss_damage: 3.,
+        boss_shoot_rate: 1.3,
+        drop_rate: 0.85,
+        extra_enemies: 0,
+        curse_chance: 0.,
+    },
+];
 
 pub const WALL_SCALE: f32 = 1.;
 
 pub const ROOM_WIDTH: usize = 15;
 pub const ROOM_HEIGHT: usize = 9;
 
+/// Characters of the room layouts that stand for tiles and surfaces, enemy definitions can't use them.
+///
+pub const LAYOUT_RESERVED_CHARS: &str = "#.vxXfFdhpgr0123456789iuz ";
+
 pub const ROOM_LAYOUT_START: &str = 
 "
 #######d#######
@@ -127,11 +324,44 @@ d             d
 #             #
 #######d#######
 ",
+"
+#######d#######
+#             #
+#  iiiiiiiii  #
+#  iiiiiiiii  #
+d  iiii.iiii  d
+#  iiiiiiiii  #
+#  iiiiiiiii  #
+#             #
+#######d#######
+",
 ];
 
 pub const ROOM_LAYOUTS_MOB: &[&str] = &[
 "
 #######d#######
+#c     .     c#
+#  ...   ...  #
+#      .      #
+d   .     .   d
+#      .      #
+#  ...   ...  #
+#c     .     c#
+#######d#######
+",
+"
+#######d#######
+#.           .#
+#  yy     yy  #
+#  yy  .  yy  #
+d     ...     d
+#  yy  .  yy  #
+#  yy     yy  #
+#.           .#
+#######d#######
+",
+"
+#######d#######
 #m           m#
 #....     ....#
 #             #
@@ -218,6 +448,131 @@ d      b      d
 #             #
 #######d#######
 ",
+"
+#######d#######
+#             #
+#   1     r   #
+#             #
+d      m      d
+#             #
+#   r     1   #
+#             #
+#######d#######
+1 plate doors
+",
+"
+#######d#######
+#2            #
+#             #
+#    .ggg.    #
+d    g   g    d
+#    .ggg.    #
+#             #
+#s    vvv    3#
+#######d#######
+2 switch gates 3,6 3,7 3,8 4,5 4,9 5,6 5,7 5,8
+3 plate reward
+3 plate spikes 7,6 7,7 7,8
+",
+"
+#######d#######
+#b           b#
+#  xXxXxXxXx  #
+#             #
+d  XxXxXxXxX  d
+#             #
+#  xXxXxXxXx  #
+#             #
+#######d#######
+",
+"
+#######d#######
+#f           f#
+#             #
+#   .  F  .   #
+d    m   m    d
+#   .     .   #
+#             #
+#f           f#
+#######d#######
+",
+"
+#######d#######
+#uuu   b   uuu#
+#uuu       uuu#
+#    zzzzz    #
+d  s   .   s  d
+#    zzzzz    #
+#uuu       uuu#
+#uuu   b   uuu#
+#######d#######
+",
+"
+#######d#######
+#.           .#
+#             #
+#   .     .   #
+d      S      d
+#   .     .   #
+#             #
+#.           .#
+#######d#######
+",
+"
+###T###d###T###
+#             #
+T   b     b   T
+#      .      #
+d     .t.     d
+#      .      #
+T             T
+#             #
+###T###d###T###
+",
+"
+#######d#######
+#             #
+#  ..     ..  #
+#  .   w   .  #
+d             d
+#  .   w   .  #
+#  ..     ..  #
+#             #
+#######d#######
+",
+"
+#######d#######
+#             #
+#  .       .  #
+#      n      #
+d   .     .   d
+#      n      #
+#  .       .  #
+#             #
+#######d#######
+",
+"
+#######d#######
+#             #
+#  W       .  #
+#      .      #
+d    .   .    d
+#      .      #
+#  .       W  #
+#             #
+#######d#######
+",
+"
+#######d#######
+#..         ..#
+#.           .#
+#             #
+d      O      d
+#             #
+#.           .#
+#..         ..#
+#######d#######
+",
 ];
 
 pub const ROOM_LAYOUTS_BOSS: &[&str] = &[
@@ -0,0 +1,82 @@
+use ggez::{
+    graphics::{self, DrawMode, DrawParam, Mesh, Color},
+    GameResult,
+    Context,
+};
+use crate::{
+    utils::*,
+    consts::*,
+};
+use glam::f32::Vec2;
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub enum CreepTag {
+    Player,
+    Enemy,
+}
+
+/// A puddle left behind by an actor, it dries up after `CREEP_LIFETIME` seconds.
+///
+#[derive(Clone, Debug, Copy)]
+pub struct Creep {
+    pub pos: Vec2,
+    /// Damage per second to whoever stands in the puddle.
+    ///
+    pub damage: f32,
+    pub slow: f32,
+    pub lifetime: f32,
+    pub tag: CreepTag,
+}
+
+impl Creep {
+    pub fn new(pos: Vec2, damage: f32, slow: f32, tag: CreepTag) -> Self {
+        Self {
+            pos,
+            damage,
+            slow,
+            lifetime: CREEP_LIFETIME,
+            tag,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) { self.lifetime = f32::max(0., self.lifetime - delta_time); }
+
+    pub fn is_dry(&self) -> bool { self.lifetime == 0. }
+
+    pub fn radius(&self, sw: f32, sh: f32) -> f32 {
+        f32::min(sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32) * CREEP_SCALE / 2.
+    }
+
+    /// Returns the strongest damage and slow of all puddles with the given tag that contain `pos`.
+    /// Damage is per second, slow is the movement-speed multiplier.
+    ///
+    pub fn effect_at(creep: &[Creep], pos: Vec2, tag: CreepTag, sw: f32, sh: f32) -> Option<(f32, f32)> {
+        creep.iter()
+            .filter(|c| c.tag == tag && c.pos.distance(pos) < c.radius(sw, sh))
+            .map(|c| (c.damage, c.slow))
+            .reduce(|a, b| (f32::max(a.0, b.0), f32::min(a.1, b.1)))
+    }
+
+    /// Puddles fade out as they dry up.
+    ///
+    pub fn draw(&self, ctx: &mut Context, conf: &Config) -> GameResult {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let radius = self.radius(sw, sh);
+        let alpha = 0.6 * self.lifetime / CREEP_LIFETIME;
+
+        let color = match self.tag {
+            CreepTag::Player => Color::new(0.6, 0.8, 0.2, alpha),
+            CreepTag::Enemy => Color::new(0.5, 0.05, 0.05, alpha),
+        };
+
+        let puddle = Mesh::new_circle(ctx, DrawMode::fill(), self.pos, radius, 0.5, color)?;
+        graphics::draw(ctx, &puddle, DrawParam::default())?;
+
+        if conf.draw_bcircle_model {
+            let outline = Mesh::new_circle(ctx, DrawMode::stroke(2.0), self.pos, radius, 0.5, Color::BLUE)?;
+            graphics::draw(ctx, &outline, DrawParam::default())?;
+        }
+
+        Ok(())
+    }
+}
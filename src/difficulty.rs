@@ -0,0 +1,51 @@
+use crate::{
+    consts::*,
+    aiming::Accuracy,
+};
+
+/// Scaling of a single floor.
+/// Dungeon generation and enemy construction read all of their numbers from it.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    pub room_count: (usize, usize),
+    pub item_rooms: usize,
+    pub enemy_health: f32,
+    pub enemy_damage: f32,
+    pub enemy_speed: f32,
+    pub enemy_shoot_rate: f32,
+    pub enemy_accuracy: Accuracy,
+    pub boss_health: f32,
+    pub boss_damage: f32,
+    pub boss_shoot_rate: f32,
+    pub drop_rate: f64,
+    pub extra_enemies: usize,
+    pub curse_chance: f64,
+}
+
+impl Difficulty {
+    /// Levels up to `MAX_LEVEL` come from `DIFFICULTY_CURVE`.
+    /// Deeper levels keep growing from the last entry of the curve, that is what drives endless mode.
+    ///
+    pub fn for_level(level: u32) -> Self {
+        let index = (level.clamp(1, DIFFICULTY_CURVE.len() as u32) - 1) as usize;
+        let base = DIFFICULTY_CURVE[index];
+        let depth = level.saturating_sub(DIFFICULTY_CURVE.len() as u32);
+        let d = depth as f32;
+
+        Self {
+            room_count: (
+                usize::min(base.room_count.0 + depth as usize, DUNGEON_MAX_ROOMS),
+                usize::min(base.room_count.1 + depth as usize, DUNGEON_MAX_ROOMS),
+            ),
+            enemy_health: base.enemy_health + d * ENDLESS_HEALTH_SCALE,
+            enemy_speed: f32::min(base.enemy_speed + d * ENDLESS_SPEED_SCALE, ENDLESS_SPEED_MAX),
+            enemy_shoot_rate: base.enemy_shoot_rate + d * ENDLESS_SHOOT_RATE_SCALE,
+            boss_health: base.boss_health + d * ENDLESS_HEALTH_SCALE,
+            boss_shoot_rate: base.boss_shoot_rate + d * ENDLESS_SHOOT_RATE_SCALE,
+            extra_enemies: base.extra_enemies + depth as usize,
+            curse_chance: f64::min(base.curse_chance + depth as f64 * ENDLESS_CURSE_CHANCE, ENDLESS_CURSE_CHANCE_MAX),
+            ..base
+        }
+    }
+}
@@ -1,21 +1,28 @@
 use ggez::{
-    graphics::{self, DrawParam, Rect},
+    graphics::{self, DrawParam, DrawMode, Rect, MeshBuilder, Color},
     GameResult,
+    GameError,
     Context,
     audio::SoundSource,
 };
 use crate::{
     enemies::*,
+    bestiary::Bestiary,
     utils::*,
     consts::*,
     traits::*,
     items::*,
     shots::*,
     player::*,
+    puzzles::*,
+    creep::*,
+    difficulty::Difficulty,
+    pathfinding::{FlowField, MovementLayer},
+    flocking::AllyGrid,
 };
 use std::{
     any::Any,
-    collections::VecDeque,
+    collections::{VecDeque, HashMap, HashSet},
     f32::consts::PI,
     cmp::Reverse,
 };
@@ -51,31 +58,81 @@ pub struct Room {
     pub enemies: Vec<Box<dyn Actor>>,
     pub shots: Vec<Shot>,
     pub drops: Vec<Collectable>,
+    pub creep: Vec<Creep>,
+    pub surfaces: [[Surface; ROOM_WIDTH]; ROOM_HEIGHT],
+    pub puzzle: Puzzle,
+    pub difficulty: Difficulty,
+    pub flow_fields: HashMap<(MovementLayer, u32), FlowField>,
 }
 
 impl Room {
     pub fn update(&mut self, ctx: &mut Context, conf: &mut Config, _player: &mut Player, _delta_time: f32) -> GameResult {
         let (sw, sh) = (conf.screen_width, conf.screen_height);
-        let target_grid = &self.get_target_distance_grid(_player.get_pos(), sw, sh);
+        let keys = self.enemies.iter().map(|e| (e.get_layer(), e.get_clearance(sw, sh))).collect::<HashSet<_>>();
+        let mut grids = HashMap::new();
+        for (layer, clearance) in keys {
+            let grid = *grids.entry(layer).or_insert_with(|| self.get_layer_grid(layer, sw, sh));
+            self.flow_fields.entry((layer, clearance))
+                .or_insert_with(|| FlowField::new(&grid, _player.get_pos(), clearance, sw, sh))
+                .update(&grid, _player.get_pos(), sw, sh);
+        }
+
+        _player.set_surface(Room::get_surface(&self.surfaces, _player.get_pos(), sw, sh));
+        for enemy in self.enemies.iter_mut() {
+            let surface = match enemy.get_layer().is_grounded() {
+                true => Room::get_surface(&self.surfaces, enemy.get_pos(), sw, sh),
+                false => Surface::Normal,
+            };
+            enemy.set_surface(surface);
+        }
+        for drop in self.drops.iter_mut() {
+            drop.set_surface(Room::get_surface(&self.surfaces, drop.get_pos(), sw, sh));
+        }
 
         for shot in self.shots.iter_mut() {
             shot.update(ctx, conf, _delta_time)?;
         }
 
+        let allies = AllyGrid::new(&self.enemies, sw, sh);
         for enemy in self.enemies.iter_mut() {
-            enemy.act(sw, sh, target_grid, &self.obstacles, &mut self.shots, _player)?;
+            let act = ActContext {
+                sw,
+                sh,
+                flow: &self.flow_fields[&(enemy.get_layer(), enemy.get_clearance(sw, sh))],
+                obstacles: &self.obstacles,
+                allies: &allies,
+                player: _player,
+            };
+            enemy.act(&act, &mut self.shots)?;
             enemy.update(ctx, conf, _delta_time)?;
         }
+        let spawns = self.enemies.iter_mut()
+            .flat_map(|e| e.take_spawns(sw, sh))
+            .collect::<Vec<_>>();
+        self.enemies.extend(spawns);
 
         for drop in self.drops.iter_mut() {
             drop.update(ctx, conf, _delta_time)?;
         }
 
+        for obst in self.obstacles.iter_mut() {
+            obst.update(conf, _delta_time)?;
+            obst.act(sw, sh, &mut self.shots, _player)?;
+        }
+
+        self.update_creep(conf, _player, _delta_time);
+        self.update_puzzle(sw, sh, _player.get_pos());
+
+        let children = self.enemies.iter_mut()
+            .filter(|e| e.get_state() == ActorState::Dead)
+            .flat_map(|e| e.on_death(&self.grid, sw, sh))
+            .collect::<Vec<_>>();
         let dead_enemies = self.enemies.iter()
             .enumerate()
             .filter(|e| e.1.get_state() == ActorState::Dead)
             .map(|e| e.0).collect::<Vec<_>>();
         for (i,d) in dead_enemies.iter().enumerate() { self.enemies.remove(d - i); }
+        self.enemies.extend(children);
 
         let dead_shots = self.shots.iter()
             .enumerate()
@@ -91,8 +148,13 @@ impl Room {
             .filter(|d| d.1.state == CollectableState::Consumed)
             .map(|d| d.0).collect::<Vec<_>>();
         for (i,d) in dead_drops.iter().enumerate() { self.drops.remove(d - i); }
+
+        self.creep.retain(|c| !c.is_dry());
         
-        if self.enemies.is_empty() {
+        if self.enemies.iter().all(|e| !e.blocks_clear()) && self.puzzle.is_solved() {
+            if self.state != RoomState::Cleared {
+                for enemy in self.enemies.iter_mut() { enemy.shut_off(); }
+            }
             self.state = RoomState::Cleared;
 
             match self.tag {
@@ -147,13 +209,81 @@ impl Room {
             graphics::draw(ctx, conf.assets.sprites.get("instructions").unwrap(), draw_params.scale(scale * 0.7).offset([-0.2, -0.2]))?;
         }
 
-        for obst in self.obstacles.iter() { obst.draw(ctx, conf)?; }
+        self.draw_surfaces(ctx, sw, sh)?;
+
+        for obst in self.obstacles.iter().filter(|o| o.get_tag().is_floor()) { obst.draw(ctx, conf)?; }
+
+        for creep in self.creep.iter() { creep.draw(ctx, conf)?; }
+
+        for obst in self.obstacles.iter().filter(|o| !o.get_tag().is_floor()) { obst.draw(ctx, conf)?; }
 
         for drop in self.drops.iter() { drop.draw(ctx, conf)?; }
 
         for shot in self.shots.iter() { shot.draw(ctx, conf)?; }
 
-        for enemy in self.enemies.iter() { enemy.draw(ctx, conf)?; }
+        let mut enemies = self.enemies.iter().collect::<Vec<_>>();
+        enemies.sort_by_key(|e| e.draw_layer());
+        for enemy in enemies { enemy.draw(ctx, conf)?; }
+
+        Ok(())
+    }
+
+    /// Leaves new creep behind the actors, then applies the damage and slow of the puddles.
+    /// Player creep hurts enemies and enemy creep hurts the player, the slow is picked up by `velocity_lerp` on the next frame.
+    /// Creep damage is per second, the player is only hurt once per invulnerability window so it takes what the window adds up to.
+    ///
+    fn update_creep(&mut self, conf: &Config, player: &mut Player, delta_time: f32) {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+
+        for creep in self.creep.iter_mut() {
+            creep.update(delta_time);
+        }
+
+        let mut new_creep = self.enemies.iter_mut()
+            .filter_map(|e| e.leave_creep())
+            .collect::<Vec<_>>();
+        if let Some(c) = player.leave_creep() { new_creep.push(c); }
+        self.creep.extend(new_creep);
+
+        for enemy in self.enemies.iter_mut() {
+            let effect = match enemy.get_layer().is_grounded() {
+                true => Creep::effect_at(&self.creep, enemy.get_pos(), CreepTag::Player, sw, sh),
+                false => None,
+            };
+            if let Some((damage, _)) = effect { enemy.damage_over_time(damage * delta_time); }
+            enemy.set_slow(effect.map_or(1., |(_, slow)| slow));
+        }
+
+        let effect = Creep::effect_at(&self.creep, player.get_pos(), CreepTag::Enemy, sw, sh);
+        if let Some((damage, _)) = effect { player.damage(damage * PLAYER_DAMAGED_COOLDOWN); }
+        player.set_slow(effect.map_or(1., |(_, slow)| slow));
+    }
+
+    fn draw_surfaces(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (bw, bh) = (sw / self.width, sh / self.height);
+        let mut mb = MeshBuilder::new();
+        let mut is_empty = true;
+
+        for (i, row) in self.surfaces.iter().enumerate() {
+            for (j, surface) in row.iter().enumerate() {
+                let rect = Rect::new(j as f32 * bw, i as f32 * bh, bw, bh);
+
+                match surface {
+                    Surface::Normal => continue,
+                    Surface::Ice => mb.rectangle(DrawMode::fill(), rect, Color::new(0.7, 0.9, 1., 0.6))?,
+                    Surface::Sticky => mb.rectangle(DrawMode::fill(), rect, Color::new(0.4, 0.3, 0.1, 0.6))?,
+                    Surface::SpeedPad => mb
+                        .rectangle(DrawMode::fill(), rect, Color::new(1., 0.8, 0.2, 0.5))?
+                        .rectangle(DrawMode::fill(), Rect::new(rect.x + bw * 0.4, rect.y + bh * 0.1, bw * 0.2, bh * 0.8), Color::new(1., 1., 0.6, 0.7))?,
+                };
+                is_empty = false;
+            }
+        }
+
+        if !is_empty {
+            let mesh = mb.build(ctx)?;
+            graphics::draw(ctx, &mesh, DrawParam::default())?;
+        }
 
         Ok(())
     }
@@ -170,20 +300,18 @@ impl Room {
         coords + dims / 2.
     }
 
-    fn parse_layout(sw: f32, sh: f32, rw: f32, rh: f32, layout: &str, door_connects: &[Option<((usize, usize), Direction)>; 4], level: u32) -> (Vec<Box<dyn Stationary>>, Vec<Box<dyn Actor>>, Vec<usize>, [[i32; ROOM_WIDTH]; ROOM_HEIGHT]) {
+    fn parse_layout(sw: f32, sh: f32, rw: f32, rh: f32, layout: &str, door_connects: &[Option<((usize, usize), Direction)>; 4], cycle: SpikesCycle) -> (Vec<Box<dyn Stationary>>, Vec<(char, Vec2)>, Vec<usize>, [[i32; ROOM_WIDTH]; ROOM_HEIGHT]) {
         let mut doors: Vec<usize> = Vec::new();
         let mut obstacles: Vec<Box<dyn Stationary>> = Vec::new(); 
-        let mut enemies: Vec<Box<dyn Actor>> = Vec::new();
+        let mut spawns: Vec<(char, Vec2)> = Vec::new();
         let mut grid = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
 
         let mut door_index = 0_usize;
 
-        let enemy_damage_amplifier = (level as f32 / ENEMY_DAMAGE).clamp(0.5, 3.);
-
         for (i, c) in layout.chars().enumerate() {
             match c {
-                '#'|'.'|'v'|'d'|'h'|'p' => {
-                    if c != 'v' { grid[i / ROOM_WIDTH as usize][i % ROOM_WIDTH as usize] = i32::MIN; }
+                '#'|'.'|'v'|'x'|'X'|'f'|'F'|'d'|'h'|'p'|'g'|'r'|'0'..='9' => {
+                    if !matches!(c, 'v'|'x'|'X'|'0'..='9') { grid[i / ROOM_WIDTH as usize][i % ROOM_WIDTH as usize] = i32::MIN; }
 
                     obstacles.push(Box::new(Block {
                         pos: Room::get_entity_pos(sw, sh, rw, rh, i).into(),
@@ -203,7 +331,30 @@ impl Room {
                             }
                             '#' => BlockTag::Wall,
                             '.' => BlockTag::Stone,
-                            'v' => BlockTag::Spikes,
+                            'v' => BlockTag::Spikes {
+                                state: SpikesState::Extended,
+                                timer: 0.,
+                                cycle: None,
+                            },
+                            'x' | 'X' => {
+                                let state = if c == 'x' { SpikesState::Retracted } else { SpikesState::Extended };
+                                BlockTag::Spikes {
+                                    state,
+                                    timer: cycle.duration(state),
+                                    cycle: Some(cycle),
+                                }
+                            },
+                            'f' | 'F' => BlockTag::Fire {
+                                health: FIRE_HEALTH,
+                                is_shooter: c == 'F',
+                                shoot_timeout: 1. / FIRE_SHOOT_RATE,
+                            },
+                            'g' => BlockTag::Gate(false),
+                            'r' => BlockTag::Rock,
+                            '0'..='9' => BlockTag::Plate {
+                                channel: c.to_digit(10).unwrap() as usize,
+                                is_pressed: false,
+                            },
                             'h' => {
                                 doors.push(obstacles.len());
                                 BlockTag::Hatch(false)
@@ -213,58 +364,122 @@ impl Room {
                         },
                     }));
                 },
-                'm' => {
-                    enemies.push(Box::new(EnemyMask {
-                        props: ActorProps {
-                            pos: Room::get_entity_pos(sw, sh, rw, rh, i).into(),
-                            scale: Vec2::splat(ENEMY_SCALE),
-                            ..Default::default()
-                        },
-                        damage: ENEMY_DAMAGE * enemy_damage_amplifier,
-                        ..Default::default()
-                    }));
-                },
-                'b' => {
-                    enemies.push(Box::new(EnemyBlueGuy {
-                        props: ActorProps {
-                            pos: Room::get_entity_pos(sw, sh, rw, rh, i).into(),
-                            scale: Vec2::splat(ENEMY_SCALE),
-                            ..Default::default()
-                        },
-                        damage: ENEMY_DAMAGE * enemy_damage_amplifier,
-                        ..Default::default()
-                    }));
-                },
-                's' => {
-                    enemies.push(Box::new(EnemySlime {
-                        props: ActorProps {
-                            pos: Room::get_entity_pos(sw, sh, rw, rh, i).into(),
-                            scale: Vec2::new(ENEMY_SCALE, ENEMY_SCALE * 0.5),
-                            ..Default::default()
-                        },
-                        damage: ENEMY_DAMAGE * enemy_damage_amplifier,
-                        ..Default::default()
-                    }));
-                },
-                'B' => {
-                    enemies.push(Box::new(BossWeirdBall {
-                        props: ActorProps {
-                            pos: Room::get_entity_pos(sw, sh, rw, rh, i).into(),
-                            scale: Vec2::splat(ENEMY_SCALE * 2.),
-                            ..Default::default()
-                        },
-                        damage: ENEMY_DAMAGE * 2. * enemy_damage_amplifier,
-                        ..Default::default()
-                    }));
-                },
+                c if !LAYOUT_RESERVED_CHARS.contains(c) => spawns.push((c, Room::get_entity_pos(sw, sh, rw, rh, i))),
                 _ => (),
             }
         }
 
-        (obstacles, enemies, doors, grid)
+        (obstacles, spawns, doors, grid)
+    }
+
+    /// Creates the enemy for the given layout character, scaled by the difficulty of the floor.
+    ///
+    pub fn spawn_enemy(c: char, pos: Vec2, difficulty: &Difficulty, bestiary: &Bestiary) -> Option<Box<dyn Actor>> {
+        Some(Box::new(Room::build_enemy(c, pos, difficulty, bestiary)?))
+    }
+
+    /// Creates the enemy for the given layout character together with the body of a worm, laid out on the free tiles of the grid.
+    ///
+    pub fn spawn_enemies(c: char, pos: Vec2, difficulty: &Difficulty, bestiary: &Bestiary, grid: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], sw: f32, sh: f32) -> Vec<Box<dyn Actor>> {
+        let mut enemy = match Room::build_enemy(c, pos, difficulty, bestiary) {
+            Some(enemy) => enemy,
+            None => return Vec::new(),
+        };
+        let body = enemy.grow_body(grid, sw, sh);
+
+        std::iter::once(enemy).chain(body)
+            .map(|e| Box::new(e) as Box<dyn Actor>)
+            .collect()
+    }
+
+    fn build_enemy(c: char, pos: Vec2, difficulty: &Difficulty, bestiary: &Bestiary) -> Option<Enemy> {
+        let enemy = Room::scale_enemy(bestiary.get(c)?, difficulty);
+
+        Some(Enemy {
+            props: ActorProps {
+                pos: pos.into(),
+                ..enemy.props
+            },
+            ..enemy
+        })
+    }
+
+    /// Scales the stats of an enemy and of the minions it spawns by the difficulty.
+    ///
+    fn scale_enemy(enemy: &Enemy, difficulty: &Difficulty) -> Enemy {
+        let (damage, health, shoot_rate) = match enemy.is_boss {
+            true => (difficulty.boss_damage, difficulty.boss_health, difficulty.boss_shoot_rate),
+            false => (difficulty.enemy_damage, difficulty.enemy_health, difficulty.enemy_shoot_rate),
+        };
+        let spawner = enemy.spawner.clone().map(|spawner| Spawner {
+            template: spawner.template.map(|t| Box::new(Room::scale_enemy(&t, difficulty))),
+            brood: Brood::default(),
+            ..spawner
+        });
+
+        Enemy {
+            damage: enemy.damage * damage,
+            health: enemy.health * health,
+            max_health: enemy.max_health * health,
+            speed: enemy.speed * difficulty.enemy_speed,
+            shoot_rate: enemy.shoot_rate * shoot_rate,
+            accuracy: enemy.accuracy.scaled(&difficulty.enemy_accuracy),
+            spawner,
+            ..enemy.clone()
+        }
+    }
+
+    /// Spawns extra enemies on random free tiles, used to fill the rooms deeper in endless mode.
+    ///
+    fn spawn_extra_enemies(&mut self, count: usize, sw: f32, sh: f32, bestiary: &Bestiary) {
+        let free = (0..ROOM_HEIGHT * ROOM_WIDTH)
+            .map(|k| (k / ROOM_WIDTH, k % ROOM_WIDTH))
+            .filter(|&(i, j)| self.grid[i][j] == 0 && i > 1 && j > 1 && i < ROOM_HEIGHT - 2 && j < ROOM_WIDTH - 2)
+            .collect::<Vec<_>>();
+        if free.is_empty() { return; }
+
+        for _ in 0..count {
+            let (i, j) = free[thread_rng().gen_range(0..free.len())];
+            let c = ENDLESS_EXTRA_ENEMIES[thread_rng().gen_range(0..ENDLESS_EXTRA_ENEMIES.len())];
+
+            let enemies = Room::spawn_enemies(c, room_coords_to_pos(i, j, sw, sh), &self.difficulty, bestiary, &self.grid, sw, sh);
+            self.enemies.extend(enemies);
+        }
+    }
+
+    /// Surfaces are laid under the tiles, `i` is ice, `u` is a sticky floor and `z` a speed pad.
+    ///
+    fn parse_surfaces(layout: &str) -> [[Surface; ROOM_WIDTH]; ROOM_HEIGHT] {
+        let mut surfaces = [[Surface::Normal; ROOM_WIDTH]; ROOM_HEIGHT];
+
+        for (i, c) in layout.chars().enumerate().take(ROOM_WIDTH * ROOM_HEIGHT) {
+            surfaces[i / ROOM_WIDTH][i % ROOM_WIDTH] = match c {
+                'i' => Surface::Ice,
+                'u' => Surface::Sticky,
+                'z' => Surface::SpeedPad,
+                _ => Surface::Normal,
+            };
+        }
+
+        surfaces
     }
 
-    fn generate_room(screen: (f32, f32), dungeon_coords: (usize, usize), door_connects: [Option<((usize, usize), Direction)>; 4], tag: RoomTag, level: u32) -> Room {
+    /// A room holds loot while it has an untaken pedestal item or pickups lying around.
+    ///
+    pub fn has_loot(&self) -> bool {
+        !self.drops.is_empty() || self.obstacles.iter().any(|o| matches!(o.get_tag(), BlockTag::Pedestal(Some(_))))
+    }
+
+    pub fn get_surface(surfaces: &[[Surface; ROOM_WIDTH]], pos: Vec2, sw: f32, sh: f32) -> Surface {
+        let (i, j) = pos_to_room_coords(pos, sw, sh);
+
+        match (0..ROOM_HEIGHT).contains(&i) && (0..ROOM_WIDTH).contains(&j) {
+            true => surfaces[i][j],
+            false => Surface::Normal,
+        }
+    }
+
+    fn generate_room(screen: (f32, f32), dungeon_coords: (usize, usize), door_connects: [Option<((usize, usize), Direction)>; 4], tag: RoomTag, difficulty: &Difficulty, bestiary: &Bestiary) -> GameResult<Room> {
         let (sw, sh) = screen;
         
         let state = RoomState::Undiscovered;
@@ -273,7 +488,7 @@ impl Room {
         let shots = Vec::<Shot>::new();
         let drops = Vec::<Collectable>::new();
 
-        let mut layout = String::from(match tag {
+        let layout = match tag {
             RoomTag::Start => ROOM_LAYOUT_START,
             RoomTag::Mob => {
                 let layout_index = thread_rng().gen_range(0..ROOM_LAYOUTS_MOB.len()) as usize;
@@ -291,12 +506,28 @@ impl Room {
                 let layout_index = thread_rng().gen_range(0..ROOM_LAYOUTS_BOSS.len()) as usize;
                 ROOM_LAYOUTS_BOSS[layout_index]
             }
-        });
-        layout = layout.trim().split('\n').map(|l| l.trim()).collect::<String>();
-
-        let (obstacles, enemies, doors, grid) = Room::parse_layout(sw, sh, width, height, &layout, &door_connects, level);
+        };
+        let lines = layout.trim().split('\n').map(|l| l.trim()).collect::<Vec<_>>();
+        let (tiles, wiring) = lines.split_at(usize::min(ROOM_HEIGHT, lines.len()));
+        let (puzzle, cycle) = Room::parse_wiring(wiring).map_err(Into::<GameError>::into)?;
+
+        let (mut obstacles, spawns, doors, mut grid) = Room::parse_layout(sw, sh, width, height, &tiles.concat(), &door_connects, cycle);
+        for (_, pos) in spawns.iter().filter(|(c, _)| bestiary.get(*c).is_some_and(|e| e.embedded)) {
+            let (i, j) = pos_to_room_coords(*pos, sw, sh);
+            grid[i][j] = i32::MIN;
+            obstacles.push(Box::new(Block {
+                pos: (*pos).into(),
+                scale: Vec2::splat(WALL_SCALE),
+                tag: BlockTag::Wall,
+            }));
+        }
+        let surfaces = Room::parse_surfaces(&tiles.concat());
+        Room::wire_mechanisms(&puzzle, &mut obstacles, &mut grid, sw, sh);
+        let enemies = spawns.into_iter()
+            .flat_map(|(c, pos)| Room::spawn_enemies(c, pos, difficulty, bestiary, &grid, sw, sh))
+            .collect::<Vec<_>>();
 
-        Room {
+        let mut room = Room {
             tag,
             state,
             width,
@@ -308,81 +539,219 @@ impl Room {
             enemies,
             shots,
             drops,
+            creep: Vec::new(),
+            surfaces,
+            puzzle,
+            difficulty: *difficulty,
+            flow_fields: HashMap::new(),
+        };
+
+        if tag == RoomTag::Mob {
+            room.spawn_extra_enemies(difficulty.extra_enemies, sw, sh, bestiary);
         }
-    }
 
-    pub fn get_target_distance_grid(&self, target: Vec2, sw: f32, sh: f32) -> [[i32; ROOM_WIDTH]; ROOM_HEIGHT] {
-        let mut grid = self.grid;
-        let (ti, tj) = pos_to_room_coords(target, sw, sh);
-        if !(0..ROOM_HEIGHT).contains(&ti) || !(0..ROOM_WIDTH).contains(&tj) { return grid; }
+        Ok(room)
+    }
 
-        grid[ti][tj] = i32::MAX;
-        let mut q = VecDeque::<(usize, usize)>::new();
-        q.push_back((ti, tj));
+    /// Parses the lines below the tiles of a layout, a `cycle <extended> <warning> <retracted>` line sets the timing
+    /// of the cycling spikes and the other lines wire the puzzle.
+    ///
+    pub fn parse_wiring(lines: &[&str]) -> Result<(Puzzle, SpikesCycle), Errors> {
+        let (cycles, wiring): (Vec<&str>, Vec<&str>) = lines.iter().partition(|l| l.starts_with("cycle"));
+        let cycle = match cycles[..] {
+            [] => SpikesCycle::default(),
+            [line] => SpikesCycle::parse(line)?,
+            _ => return Err(Errors::LayoutParse(cycles.join("\n"))),
+        };
 
-        while !q.is_empty() {
-            let (i, j) = q.pop_front().unwrap();
+        Ok((Puzzle::parse(&wiring)?, cycle))
+    }
 
-            if i > 0               && grid[i - 1][j] == 0 { 
-                grid[i - 1][j] = grid[i][j] - 1;
-                q.push_back((i - 1, j));
-            }
-            if j > 0               && grid[i][j - 1] == 0 {
-                grid[i][j - 1] = grid[i][j] - 1;
-                q.push_back((i, j - 1));
-            }
-            if j < ROOM_WIDTH - 1  && grid[i][j + 1] == 0 {
-                grid[i][j + 1] = grid[i][j] - 1;
-                q.push_back((i, j + 1));
+    /// Turns the plates of channels wired as switches into switches.
+    /// Switches are solid, so they are also marked as obstacles in the grid.
+    ///
+    fn wire_mechanisms(puzzle: &Puzzle, obstacles: &mut [Box<dyn Stationary>], grid: &mut [[i32; ROOM_WIDTH]; ROOM_HEIGHT], sw: f32, sh: f32) {
+        for o in obstacles.iter_mut() {
+            let (i, j) = pos_to_room_coords(o.get_pos(), sw, sh);
+            let block = o.as_any_mut().downcast_mut::<Block>().unwrap();
+
+            if let BlockTag::Plate { channel, .. } = block.tag {
+                if puzzle.kind_of(channel) == MechanismKind::Switch {
+                    block.tag = BlockTag::Switch { channel, is_on: false };
+                    grid[i][j] = i32::MIN;
+                }
             }
-            if i < ROOM_HEIGHT - 1 && grid[i + 1][j] == 0 {
-                grid[i + 1][j] = grid[i][j] - 1;
-                q.push_back((i + 1, j));
+        }
+    }
+
+    /// Spikes and gates follow the wires that drive them, a gate waits until nobody stands in it before it closes.
+    ///
+    fn update_puzzle(&mut self, sw: f32, sh: f32, player_pos: Vec2) {
+        if self.puzzle.wires.is_empty() { return; }
+
+        let player_coords = pos_to_room_coords(player_pos, sw, sh);
+        let rocks = self.obstacles.iter()
+            .filter(|o| matches!(o.get_tag(), BlockTag::Rock))
+            .map(|o| pos_to_room_coords(o.get_pos(), sw, sh))
+            .collect::<Vec<_>>();
+
+        let mut channels = [true; PUZZLE_CHANNELS];
+        let mut wired = [false; PUZZLE_CHANNELS];
+
+        for o in self.obstacles.iter_mut() {
+            let coords = pos_to_room_coords(o.get_pos(), sw, sh);
+            let block = o.as_any_mut().downcast_mut::<Block>().unwrap();
+
+            let (channel, is_active) = match block.tag {
+                BlockTag::Plate { channel, .. } => {
+                    let is_pressed = coords == player_coords || rocks.contains(&coords);
+                    block.tag = BlockTag::Plate { channel, is_pressed };
+                    (channel, is_pressed)
+                },
+                BlockTag::Switch { channel, is_on } => (channel, is_on),
+                _ => continue,
+            };
+
+            wired[channel] = true;
+            channels[channel] &= is_active;
+        }
+
+        channels.iter_mut().zip(wired.iter()).for_each(|(c, w)| *c &= *w);
+
+        for (action, is_active) in self.puzzle.update(&channels) {
+            if action == PuzzleAction::SpawnReward && is_active { self.spawn_collectable((ROOM_HEIGHT / 2, ROOM_WIDTH / 2), sw, sh); }
+        }
+
+        let occupied = self.enemies.iter()
+            .map(|e| pos_to_room_coords(e.get_pos(), sw, sh))
+            .chain(std::iter::once(player_coords))
+            .collect::<Vec<_>>();
+
+        for o in self.obstacles.iter_mut() {
+            let (i, j) = pos_to_room_coords(o.get_pos(), sw, sh);
+            let block = o.as_any_mut().downcast_mut::<Block>().unwrap();
+
+            match block.tag {
+                BlockTag::Spikes { timer, cycle: None, .. } => if let Some(is_active) = self.puzzle.drives(PuzzleAction::RetractSpikes, (i, j)) {
+                    let state = if is_active { SpikesState::Retracted } else { SpikesState::Extended };
+                    block.tag = BlockTag::Spikes { state, timer, cycle: None };
+                },
+                BlockTag::Gate(is_open) => if let Some(is_active) = self.puzzle.drives(PuzzleAction::ToggleGates, (i, j)) {
+                    if is_active != is_open && (is_active || !occupied.contains(&(i, j))) {
+                        block.tag = BlockTag::Gate(is_active);
+                        self.grid[i][j] = if is_active { 0 } else { i32::MIN };
+                    }
+                },
+                _ => (),
             }
         }
+    }
+
+    /// Pushes the rock with the given obstacle index one tile in the given direction.
+    /// Rocks can only be pushed onto free floor, plates or spikes.
+    ///
+    pub fn push_rock(&mut self, index: usize, dir: Vec2, sw: f32, sh: f32) -> bool {
+        let (i, j) = pos_to_room_coords(self.obstacles[index].get_pos(), sw, sh);
+        let (ni, nj) = (i as i32 + dir.y.round() as i32, j as i32 + dir.x.round() as i32);
+
+        if !(0..ROOM_HEIGHT as i32).contains(&ni) || !(0..ROOM_WIDTH as i32).contains(&nj) { return false; }
+
+        let (ni, nj) = (ni as usize, nj as usize);
+
+        if self.grid[ni][nj] != 0 { return false; }
+        if self.obstacles.iter().any(|o| {
+            pos_to_room_coords(o.get_pos(), sw, sh) == (ni, nj) && !matches!(o.get_tag(), BlockTag::Plate { .. } | BlockTag::Spikes { .. })
+        }) { return false; }
+
+        let block = self.obstacles[index].as_any_mut().downcast_mut::<Block>().unwrap();
+        block.pos = room_coords_to_pos(ni, nj, sw, sh).into();
+        self.grid[i][j] = 0;
+        self.grid[ni][nj] = i32::MIN;
+
+        true
+    }
+
+    /// Obstacle grid as seen by the given movement layer, the ground layer uses the room grid as is.
+    ///
+    pub fn get_layer_grid(&self, layer: MovementLayer, sw: f32, sh: f32) -> [[i32; ROOM_WIDTH]; ROOM_HEIGHT] {
+        if layer == MovementLayer::Ground { return self.grid; }
+
+        let mut grid = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
+        for obst in self.obstacles.iter().filter(|o| layer.is_blocked_by(&o.get_tag())) {
+            let (i, j) = pos_to_room_coords(obst.get_pos(), sw, sh);
+            grid[i][j] = i32::MIN;
+        }
 
         grid
     }
 
+    pub fn get_target_distance_grid(&self, target: Vec2, sw: f32, sh: f32) -> [[i32; ROOM_WIDTH]; ROOM_HEIGHT] {
+        *FlowField::new(&self.grid, target, 1, sw, sh).get_grid()
+    }
+
     fn generate_collectable(&mut self, sw: f32, sh: f32) {
-        if thread_rng().gen_bool(0.8) {
-            let (mut r, mut c) = (ROOM_HEIGHT / 2, ROOM_WIDTH / 2);
-            let (bw, bh) = (sw / self.width, sh / self.height);
-            let mut visited = [[false; ROOM_WIDTH]; ROOM_HEIGHT];
-            let mut q = VecDeque::new();
-            q.push_back((r, c));
+        if thread_rng().gen_bool(f64::min(ROOM_DROP_CHANCE * self.difficulty.drop_rate, 1.)) {
+            self.spawn_collectable((ROOM_HEIGHT / 2, ROOM_WIDTH / 2), sw, sh);
+        }
+    }
 
-            while !q.is_empty() {
-                let (i, j) = q.pop_front().unwrap();
-                visited[i][j] = true;
+    /// Puts out the fire with the given obstacle index, leaving passable ashes and maybe a pickup behind.
+    ///
+    pub fn extinguish_fire(&mut self, index: usize, sw: f32, sh: f32) {
+        let (i, j) = pos_to_room_coords(self.obstacles[index].get_pos(), sw, sh);
+        let block = self.obstacles[index].as_any_mut().downcast_mut::<Block>().unwrap();
 
-                if self.grid[i][j] == 0 { r = i; c = j; break }
+        if let BlockTag::Fire { is_shooter, shoot_timeout, .. } = block.tag {
+            block.tag = BlockTag::Fire { health: 0., is_shooter, shoot_timeout };
+            self.grid[i][j] = 0;
 
-                if i > 0           && !visited[i - 1][j] { q.push_back((i - 1, j)) }
-                if j > 0           && !visited[i][j - 1] { q.push_back((i, j - 1)) }
-                if i < ROOM_HEIGHT && !visited[i + 1][j] { q.push_back((i + 1, j)) }
-                if j < ROOM_WIDTH  && !visited[i][j + 1] { q.push_back((i, j + 1)) }
+            if thread_rng().gen_bool(f64::min(FIRE_DROP_CHANCE * self.difficulty.drop_rate, 1.)) {
+                self.spawn_collectable((i, j), sw, sh);
             }
+        }
+    }
 
-            let p = thread_rng().gen_range(0..101);
+    /// Spawns a random collectable on the free tile closest to `start`.
+    ///
+    fn spawn_collectable(&mut self, start: (usize, usize), sw: f32, sh: f32) {
+        let (mut r, mut c) = start;
+        let (bw, bh) = (sw / self.width, sh / self.height);
+        let mut visited = [[false; ROOM_WIDTH]; ROOM_HEIGHT];
+        let mut q = VecDeque::new();
+        q.push_back((r, c));
+        visited[r][c] = true;
 
-            self.drops.push(Collectable {
-                props: ActorProps {
-                    pos: Vec2::new(bw * (c as f32) + bw / 2., bh * (r as f32) + bh / 2.).into(),
-                    scale: Vec2::splat(COLLECTABLE_SCALE),
-                    translation: Vec2::ZERO,
-                    forward: Vec2::ZERO,
-                    velocity: Vec2::ZERO,
-                },
-                tag: match p {
-                    0..=50 => CollectableTag::RedHeart((thread_rng().gen::<f32>() + 1.).round() / 2.),
-                    51..=65 => CollectableTag::SpeedBoost(1.3),
-                    66..=80 => CollectableTag::DamageBoost(1.5),
-                    _ => CollectableTag::ShootRateBoost(1.3),
-                },
-                state: CollectableState::Base,
-            });
+        while !q.is_empty() {
+            let (i, j) = q.pop_front().unwrap();
+
+            if self.grid[i][j] == 0 { r = i; c = j; break }
+
+            if i > 0               && !visited[i - 1][j] { visited[i - 1][j] = true; q.push_back((i - 1, j)) }
+            if j > 0               && !visited[i][j - 1] { visited[i][j - 1] = true; q.push_back((i, j - 1)) }
+            if i < ROOM_HEIGHT - 1 && !visited[i + 1][j] { visited[i + 1][j] = true; q.push_back((i + 1, j)) }
+            if j < ROOM_WIDTH - 1  && !visited[i][j + 1] { visited[i][j + 1] = true; q.push_back((i, j + 1)) }
         }
+
+        let p = thread_rng().gen_range(0..101);
+
+        self.drops.push(Collectable {
+            props: ActorProps {
+                pos: Vec2::new(bw * (c as f32) + bw / 2., bh * (r as f32) + bh / 2.).into(),
+                scale: Vec2::splat(COLLECTABLE_SCALE),
+                translation: Vec2::ZERO,
+                forward: Vec2::ZERO,
+                velocity: Vec2::ZERO,
+                surface: Surface::Normal,
+                slow: 1.,
+            },
+            tag: match p {
+                0..=50 => CollectableTag::RedHeart((thread_rng().gen::<f32>() + 1.).round() / 2.),
+                51..=65 => CollectableTag::SpeedBoost(1.3),
+                66..=80 => CollectableTag::DamageBoost(1.5),
+                _ => CollectableTag::ShootRateBoost(1.3),
+            },
+            state: CollectableState::Base,
+        });
     }
 
     fn generate_item() -> Item {
@@ -396,14 +765,24 @@ impl Room {
     }
 }
 
+/// Curses make a whole floor harder, they only show up in endless mode.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curse {
+    Lost,
+    Darkness,
+}
+
 #[derive(Debug)]
 pub struct Dungeon {
     grid: [[Option<Room>; DUNGEON_GRID_COLS]; DUNGEON_GRID_ROWS],
     level: u32,
+    curses: Vec<Curse>,
 }
 
 impl Dungeon {
-    pub fn generate_dungeon(screen: (f32, f32), level: u32) -> Self {
+    pub fn generate_dungeon(screen: (f32, f32), level: u32, bestiary: &Bestiary) -> GameResult<Self> {
+        let difficulty = Difficulty::for_level(level);
         const INIT: Option<Room> = None;
         const INIT_ROW: [Option<Room>; DUNGEON_GRID_COLS] = [INIT; DUNGEON_GRID_COLS];
         let mut grid_rooms: [[Option<Room>; DUNGEON_GRID_COLS]; DUNGEON_GRID_ROWS] = [INIT_ROW; DUNGEON_GRID_ROWS];
@@ -411,7 +790,7 @@ impl Dungeon {
         let mut room_dungeon_coords;
 
         loop {
-            let room_count = (thread_rng().gen_range(0..2) + 5 + level * 2) as usize;
+            let room_count = thread_rng().gen_range(difficulty.room_count.0..=difficulty.room_count.1);
             grid = [[0_usize; DUNGEON_GRID_COLS]; DUNGEON_GRID_ROWS];
             room_dungeon_coords = Vec::new();
             let start_room = Dungeon::get_start_room_coords();
@@ -453,10 +832,8 @@ impl Dungeon {
 
         room_dungeon_coords.sort_by_key(|k| (Dungeon::check_room_cardinals(&grid, k.0), Reverse(k.1)));
 
-        let mut special_rooms = vec![
-            RoomTag::Item,
-            RoomTag::Boss,
-        ];
+        let mut special_rooms = vec![RoomTag::Item; difficulty.item_rooms];
+        special_rooms.push(RoomTag::Boss);
 
         for ((i, j), _) in room_dungeon_coords.into_iter() {
             let mut doors = [None; 4];
@@ -476,13 +853,20 @@ impl Dungeon {
                 };
             }
 
-            grid_rooms[i][j] = Some(Room::generate_room(screen, (i, j), doors, tag, level));
+            grid_rooms[i][j] = Some(Room::generate_room(screen, (i, j), doors, tag, &difficulty, bestiary)?);
         }
 
-        Dungeon {
+        Ok(Dungeon {
             grid: grid_rooms,
             level,
-        }
+            curses: Dungeon::roll_curses(&difficulty),
+        })
+    }
+
+    fn roll_curses(difficulty: &Difficulty) -> Vec<Curse> {
+        [Curse::Lost, Curse::Darkness].into_iter()
+            .filter(|_| thread_rng().gen_bool(difficulty.curse_chance))
+            .collect()
     }
 
     pub fn get_room(&self, dungeon_coords: (usize, usize)) -> GameResult<Option<&Room>> {
@@ -501,6 +885,8 @@ impl Dungeon {
 
     pub fn get_level(&self) -> u32 { self.level }
 
+    pub fn get_curses(&self) -> &[Curse] { &self.curses }
+
     pub const fn get_start_room_coords() -> (usize, usize) { (3, 5) }
 
     pub fn update_rooms_state(&mut self, (i, j): (usize, usize)) -> GameResult {
@@ -576,13 +962,155 @@ pub enum BlockTag {
     },
     Wall,
     Stone,
-    Spikes,
+    Spikes {
+        state: SpikesState,
+        timer: f32,
+        cycle: Option<SpikesCycle>,
+    },
     Hatch(bool),
     Pedestal(Option<Item>),
+    Plate {
+        channel: usize,
+        is_pressed: bool,
+    },
+    Switch {
+        channel: usize,
+        is_on: bool,
+    },
+    Gate(bool),
+    Rock,
+    Fire {
+        health: f32,
+        is_shooter: bool,
+        shoot_timeout: f32,
+    },
+}
+
+impl BlockTag {
+    /// Floor tiles are drawn before the rest of the obstacles, so rocks can be pushed over them.
+    ///
+    pub fn is_floor(&self) -> bool {
+        matches!(self, BlockTag::Spikes { .. } | BlockTag::Plate { .. } | BlockTag::Gate(true))
+            || matches!(self, BlockTag::Fire { health, .. } if *health <= 0.)
+    }
+
+    /// Hazards hurt every actor standing on them, so enemies can be lured into them.
+    ///
+    pub fn is_hazard(&self) -> bool {
+        matches!(self, BlockTag::Spikes { state: SpikesState::Extended, .. })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SpikesState {
+    Extended,
+    Warning,
+    Retracted,
+}
+
+/// Timings of a spike trap, the warning state is the tell shown right before the spikes extend.
+/// Spikes without a cycle stay in their state until a puzzle changes it.
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpikesCycle {
+    pub extended: f32,
+    pub warning: f32,
+    pub retracted: f32,
+}
+
+impl Default for SpikesCycle {
+    fn default() -> Self {
+        Self {
+            extended: SPIKES_EXTENDED_TIME,
+            warning: SPIKES_WARNING_TIME,
+            retracted: SPIKES_RETRACTED_TIME,
+        }
+    }
+}
+
+impl SpikesCycle {
+    /// Parses a `cycle <extended> <warning> <retracted>` line, every time is in seconds and above 0.
+    ///
+    pub fn parse(line: &str) -> Result<Self, Errors> {
+        let times = line.split_whitespace()
+            .skip(1)
+            .map(|t| t.parse::<f32>().ok().filter(|t| t.is_finite() && *t > 0.))
+            .collect::<Option<Vec<_>>>();
+
+        match times.as_deref() {
+            Some(&[extended, warning, retracted]) => Ok(Self { extended, warning, retracted }),
+            _ => Err(Errors::LayoutParse(line.to_string())),
+        }
+    }
+
+    pub fn duration(&self, state: SpikesState) -> f32 {
+        match state {
+            SpikesState::Extended => self.extended,
+            SpikesState::Warning => self.warning,
+            SpikesState::Retracted => self.retracted,
+        }
+    }
+
+    pub fn next(&self, state: SpikesState) -> SpikesState {
+        match state {
+            SpikesState::Extended => SpikesState::Retracted,
+            SpikesState::Retracted => SpikesState::Warning,
+            SpikesState::Warning => SpikesState::Extended,
+        }
+    }
 }
 
 impl Stationary for Block {
-    fn update(&mut self, _conf: &mut Config, _delta_time: f32) -> GameResult { Ok(()) }
+    fn update(&mut self, _conf: &mut Config, _delta_time: f32) -> GameResult {
+        if let BlockTag::Spikes { state, timer, cycle: Some(cycle) } = self.tag {
+            let timer = f32::max(0., timer - _delta_time);
+
+            self.tag = match timer == 0. {
+                true => {
+                    let state = cycle.next(state);
+                    BlockTag::Spikes { state, timer: cycle.duration(state), cycle: Some(cycle) }
+                },
+                false => BlockTag::Spikes { state, timer, cycle: Some(cycle) },
+            };
+        }
+
+        if let BlockTag::Fire { health, is_shooter, shoot_timeout } = self.tag {
+            self.tag = BlockTag::Fire { health, is_shooter, shoot_timeout: f32::max(0., shoot_timeout - _delta_time) };
+        }
+
+        Ok(())
+    }
+
+    fn act(&mut self, _sw: f32, _sh: f32, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult {
+        if let BlockTag::Fire { health, is_shooter: true, shoot_timeout } = self.tag {
+            if health <= 0. || shoot_timeout != 0. || self.get_pos().distance(_player.get_pos()) > FIRE_SHOOT_RANGE {
+                return Ok(());
+            }
+
+            self.tag = BlockTag::Fire { health, is_shooter: true, shoot_timeout: 1. / FIRE_SHOOT_RATE };
+
+            let shot_dir = (_player.get_pos() - self.get_pos()).normalize();
+
+            _shots.push(Shot {
+                props: ActorProps {
+                    pos: self.pos,
+                    scale: Vec2::splat(SHOT_SCALE),
+                    translation: shot_dir,
+                    forward: shot_dir,
+                    velocity: Vec2::ZERO,
+                    surface: Surface::Normal,
+                    slow: 1.,
+                },
+                spawn_pos: self.pos,
+                speed: SHOT_SPEED,
+                range: FIRE_SHOOT_RANGE,
+                damage: FIRE_DAMAGE,
+                tag: ShotTag::Enemy,
+            });
+        }
+
+        Ok(())
+    }
 
     fn draw(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
         let (sw, sh) = (conf.screen_width, conf.screen_height);
@@ -604,7 +1132,7 @@ impl Stationary for Block {
             },
             BlockTag::Wall => conf.assets.sprites.get("wall").unwrap(),
             BlockTag::Stone => conf.assets.sprites.get("stone").unwrap(),
-            BlockTag::Spikes => conf.assets.sprites.get("spikes").unwrap(),
+            BlockTag::Spikes { .. } => conf.assets.sprites.get("spikes").unwrap(),
             BlockTag::Hatch(is_open) => {
                 match is_open {
                     true => conf.assets.sprites.get("hatch_open").unwrap(),
@@ -612,6 +1140,17 @@ impl Stationary for Block {
                 }
             },
             BlockTag::Pedestal(_) => conf.assets.sprites.get("item_pedestal").unwrap(),
+            BlockTag::Gate(_) | BlockTag::Rock => conf.assets.sprites.get("stone").unwrap(),
+            BlockTag::Plate { .. } | BlockTag::Switch { .. } => return self.draw_mechanism(ctx, conf),
+            BlockTag::Fire { .. } => return self.draw_fire(ctx, conf),
+        };
+
+        let color = match self.tag {
+            BlockTag::Spikes { state: SpikesState::Retracted, .. } | BlockTag::Gate(true) => Color::new(1., 1., 1., 0.3),
+            BlockTag::Spikes { state: SpikesState::Warning, timer, .. } => Color::new(1., 0.4, 0.4, 0.5 + 0.3 * (timer * 20.).sin()),
+            BlockTag::Gate(false) => Color::new(0.6, 0.6, 0.9, 1.),
+            BlockTag::Rock => Color::new(0.8, 0.6, 0.4, 1.),
+            _ => Color::WHITE,
         };
 
         let draw_params = DrawParam::default()
@@ -620,11 +1159,13 @@ impl Stationary for Block {
             .scale(self.scale_to_screen(sw, sh, sprite.dimensions()) * 1.1)
             .offset([0.5, 0.5]);
 
-        graphics::draw(ctx, sprite, draw_params)?;
+        graphics::draw(ctx, sprite, draw_params.color(color))?;
         if let BlockTag::Pedestal(Some(i)) = self.tag {
             let item_sprite = match i.tag {
                 ItemTag::Passive(p) => match p {
                     ItemPassive::IncreaseMaxHealth(_) => conf.assets.sprites.get("poop_item").unwrap(),
+                    ItemPassive::PukeTrail => conf.assets.sprites.get("shot_puke_base").unwrap(),
+                    ItemPassive::Compass | ItemPassive::Map => return self.draw_map_item(ctx, conf, p),
                 },
                 ItemTag::Active(a) => match a {
                     ItemActive::Heal(_) => conf.assets.sprites.get("heart_item").unwrap(),
@@ -649,3 +1190,109 @@ impl Stationary for Block {
 
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
 }
+
+impl Block {
+    /// The compass and the map have no sprites, they are drawn with meshes on top of the pedestal.
+    ///
+    fn draw_map_item(&self, ctx: &mut Context, conf: &mut Config, item: ItemPassive) -> GameResult {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let bbox = self.get_bbox(sw, sh);
+        let r = bbox.h * ITEM_SCALE * 0.4;
+
+        let mesh = match item {
+            ItemPassive::Compass => MeshBuilder::new()
+                .circle(DrawMode::fill(), self.pos, r, 0.5, Color::new(0.9, 0.8, 0.5, 1.))?
+                .circle(DrawMode::stroke(3.), self.pos, r, 0.5, Color::new(0.4, 0.3, 0.1, 1.))?
+                .line(&[Vec2::new(self.pos.0.x, self.pos.0.y - r * 0.8), self.pos.0], 4., Color::RED)?
+                .line(&[self.pos.0, Vec2::new(self.pos.0.x, self.pos.0.y + r * 0.8)], 4., Color::new(0.2, 0.2, 0.2, 1.))?
+                .build(ctx)?,
+            _ => {
+                let rect = Rect::new(self.pos.0.x - r, self.pos.0.y - r * 0.75, r * 2., r * 1.5);
+                MeshBuilder::new()
+                    .rectangle(DrawMode::fill(), rect, Color::new(0.95, 0.9, 0.7, 1.))?
+                    .rectangle(DrawMode::stroke(3.), rect, Color::new(0.4, 0.3, 0.1, 1.))?
+                    .line(&[Vec2::new(rect.x + r * 0.4, rect.y + r * 0.4), Vec2::new(rect.x + r, rect.y + r), Vec2::new(rect.x + r * 1.6, rect.y + r * 0.5)], 2., Color::new(0.6, 0.2, 0.2, 1.))?
+                    .build(ctx)?
+            },
+        };
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
+
+        if conf.draw_bbox_stationary { self.draw_bbox(ctx, (sw, sh))?; }
+
+        Ok(())
+    }
+
+    fn draw_fire(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let bbox = self.get_bbox(sw, sh);
+
+        let (health, is_shooter) = match self.tag {
+            BlockTag::Fire { health, is_shooter, .. } => (health, is_shooter),
+            _ => return Ok(()),
+        };
+
+        let mesh = match health > 0. {
+            true => {
+                let flicker = 1. + (thread_rng().gen::<f32>() * 2. - 1.) * 0.08;
+                let r = bbox.h * 0.4 * (0.5 + 0.5 * health / FIRE_HEALTH) * flicker;
+                let (outer, inner) = match is_shooter {
+                    true => (Color::new(0.5, 0.2, 0.9, 1.), Color::new(0.7, 0.7, 1., 1.)),
+                    false => (Color::new(1., 0.4, 0., 1.), Color::new(1., 0.9, 0.2, 1.)),
+                };
+
+                MeshBuilder::new()
+                    .circle(DrawMode::fill(), self.pos, bbox.h * 0.35, 0.5, Color::new(0.3, 0.2, 0.1, 1.))?
+                    .circle(DrawMode::fill(), Vec2::new(self.pos.0.x, self.pos.0.y - r * 0.2), r, 0.5, outer)?
+                    .circle(DrawMode::fill(), Vec2::new(self.pos.0.x, self.pos.0.y - r * 0.1), r * 0.5, 0.5, inner)?
+                    .build(ctx)?
+            },
+            false => MeshBuilder::new()
+                .circle(DrawMode::fill(), self.pos, bbox.h * 0.3, 0.5, Color::new(0.2, 0.2, 0.2, 0.6))?
+                .build(ctx)?,
+        };
+
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
+
+        if conf.draw_bbox_stationary { self.draw_bbox(ctx, (sw, sh))?; }
+
+        Ok(())
+    }
+
+    fn draw_mechanism(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let bbox = self.get_bbox(sw, sh);
+
+        let mesh = match self.tag {
+            BlockTag::Plate { is_pressed, .. } => {
+                let (inset, color) = match is_pressed {
+                    true => (0.2, Color::new(0.3, 0.3, 0.3, 1.)),
+                    false => (0.15, Color::new(0.6, 0.6, 0.6, 1.)),
+                };
+                let rect = Rect::new(bbox.x + bbox.w * inset, bbox.y + bbox.h * inset, bbox.w * (1. - 2. * inset), bbox.h * (1. - 2. * inset));
+
+                MeshBuilder::new()
+                    .rounded_rectangle(DrawMode::fill(), rect, 5., color)?
+                    .rounded_rectangle(DrawMode::stroke(3.), rect, 5., Color::BLACK)?
+                    .build(ctx)?
+            },
+            BlockTag::Switch { is_on, .. } => {
+                let color = match is_on {
+                    true => Color::GREEN,
+                    false => Color::RED,
+                };
+
+                MeshBuilder::new()
+                    .circle(DrawMode::fill(), self.pos, bbox.h * 0.3, 0.5, color)?
+                    .circle(DrawMode::stroke(3.), self.pos, bbox.h * 0.3, 0.5, Color::BLACK)?
+                    .build(ctx)?
+            },
+            _ => return Ok(()),
+        };
+
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
+
+        if conf.draw_bbox_stationary { self.draw_bbox(ctx, (sw, sh))?; }
+
+        Ok(())
+    }
+}
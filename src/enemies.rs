@@ -10,341 +10,912 @@ use crate::{
     traits::*,
     shots::*,
     player::*,
+    creep::*,
+    pathfinding::{FlowField, MovementLayer},
+    ai::*,
+    aiming::*,
+    flocking::*,
 };
-use glam::f32::{Vec2};
+use glam::f32::{Vec2, Mat2};
 use std::{
     any::Any,
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::{Rc, Weak},
+    f32::consts::TAU,
 };
 use rand::{thread_rng, Rng};
 
+/// How an enemy gets around, each variant is built on one of the behaviour traits.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Movement {
+    Still,
+    Chase,
+    Wander,
+    Flock(FlockConfig),
+    Charge(Charge),
+    Burrow(Burrow),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attack {
+    None,
+    Aimed,
+    Cross,
+    Volley(VolleyConfig),
+}
+
+/// Fan of `count` shots spread over `spread` radians, turned by `spin` radians after every volley.
+/// A spread of a full turn fires an evenly spaced ring.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VolleyConfig {
+    pub count: usize,
+    pub spread: f32,
+    pub spin: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnHit {
+    Flash,
+    Ignore,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnDeath {
+    Vanish,
+    Puddle,
+    Split(SplitConfig),
+}
+
+/// Splitting into `count` copies, each generation is scaled by `scale`, `speed` and `health`.
+/// A copy that would be smaller than `min_scale` is not spawned, so the last generation just dies.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SplitConfig {
+    pub count: usize,
+    pub scale: f32,
+    pub speed: f32,
+    pub health: f32,
+    pub min_scale: f32,
+}
+
+/// Spawning a minion every `interval` seconds while fewer than `cap` of its minions are alive.
+/// With `minions_die` its minions die along with it, otherwise they stay once it is destroyed.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnerConfig {
+    pub interval: f32,
+    pub cap: usize,
+    pub minions_die: bool,
+}
+
+#[derive(Debug, Default)]
+struct BroodState {
+    size: Cell<usize>,
+    orphaned: Cell<bool>,
+}
+
+/// Shared by a spawner and every minion it made, it counts the living minions and tells them when they are orphaned.
+/// Minions join when they hatch and leave when they die, copies of a minion are not counted.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Brood(Rc<BroodState>);
+
+impl Brood {
+    pub fn size(&self) -> usize { self.0.size.get() }
+
+    pub fn join(&self) { self.0.size.set(self.0.size.get() + 1); }
+
+    pub fn leave(&self) { self.0.size.set(self.0.size.get().saturating_sub(1)); }
+
+    pub fn orphan(&self) { self.0.orphaned.set(true); }
+
+    pub fn is_orphaned(&self) -> bool { self.0.orphaned.get() }
+}
+
+/// Minions are copies of `template`, the enemy that `minion` stands for in the bestiary.
+///
+#[derive(Clone, Debug)]
+pub struct Spawner {
+    pub config: SpawnerConfig,
+    pub minion: char,
+    pub template: Option<Box<Enemy>>,
+    pub brood: Brood,
+    pub timer: f32,
+    pub pending: usize,
+}
+
+impl Spawner {
+    pub fn new(minion: char, config: SpawnerConfig) -> Self {
+        Self {
+            config,
+            minion,
+            template: None,
+            brood: Brood::default(),
+            timer: config.interval,
+            pending: 0,
+        }
+    }
+
+    pub fn tick(&mut self, delta_time: f32) { self.timer = f32::max(0., self.timer - delta_time); }
+
+    /// Asks for a minion once the timer ran out, unless the cap is reached.
+    ///
+    pub fn request(&mut self) {
+        if self.timer == 0. && self.brood.size() + self.pending < self.config.cap {
+            self.pending += 1;
+            self.timer = self.config.interval;
+        }
+    }
+}
+
+/// A worm of `segments` segments, including the head, kept `spacing` radii apart.
+/// With `shared` health every segment drains one pool, otherwise a destroyed segment splits the body and the segment behind it leads the rest.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WormConfig {
+    pub segments: usize,
+    pub spacing: f32,
+    pub shared: bool,
+}
+
+/// Positions a segment went through, newest first.
+///
+pub type Trail = Rc<RefCell<VecDeque<Vec2>>>;
+
+/// A link of a worm, it walks the trail of the segment ahead of it as long as that segment is alive.
+///
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub index: usize,
+    pub spacing: f32,
+    pub trail: Trail,
+    pub leader: Option<Weak<RefCell<VecDeque<Vec2>>>>,
+    pub pool: Option<Rc<Cell<f32>>>,
+}
+
+impl Segment {
+    /// The trail starts out along `trail`, from the segment back to where the next one is laid.
+    ///
+    pub fn new(index: usize, leader: Option<Weak<RefCell<VecDeque<Vec2>>>>, trail: &[Vec2], spacing: f32, pool: Option<Rc<Cell<f32>>>) -> Self {
+        Self {
+            index,
+            spacing,
+            trail: Rc::new(RefCell::new(trail.iter().copied().collect())),
+            leader,
+            pool,
+        }
+    }
+
+    /// Segments lead once there is no living segment ahead of them.
+    ///
+    pub fn is_head(&self) -> bool { self.leader.as_ref().and_then(|l| l.upgrade()).is_none() }
+
+    /// The point `gap` along the trail of the leader, or the end of that trail when it is shorter.
+    ///
+    pub fn follow(&self, gap: f32) -> Option<Vec2> {
+        let leader = self.leader.as_ref()?.upgrade()?;
+        let trail = leader.borrow();
+        point_along(trail.iter(), gap)
+    }
+
+    /// Adds the position to the trail and forgets the part no follower can reach anymore.
+    ///
+    pub fn record(&self, pos: Vec2, gap: f32) {
+        let mut trail = self.trail.borrow_mut();
+        if trail.front().is_some_and(|p| p.distance(pos) < 0.5) { return; }
+        trail.push_front(pos);
+
+        let mut length = 0.;
+        let keep = trail.iter().zip(trail.iter().skip(1)).take_while(|(a, b)| {
+            length += a.distance(**b);
+            length < gap * 1.5
+        }).count() + 2;
+        trail.truncate(keep);
+    }
+}
+
+/// The point `distance` along a path of points, or its last point when the path is shorter.
+///
+fn point_along<'a>(path: impl Iterator<Item = &'a Vec2> + Clone, distance: f32) -> Option<Vec2> {
+    let mut left = distance;
+
+    for (a, b) in path.clone().zip(path.clone().skip(1)) {
+        let step = a.distance(*b);
+        if step >= left { return Some(a.lerp(*b, left / step)); }
+        left -= step;
+    }
+    path.last().copied()
+}
+
+/// `count` blockers circling `distance` radii away at `speed` radians per second, each one absorbs `health` damage before it breaks.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitConfig {
+    pub count: usize,
+    pub distance: f32,
+    pub speed: f32,
+    pub health: f32,
+}
+
+/// Blockers orbiting an enemy, they are placed relative to the enemy so they move along with it.
+/// Broken blockers keep their slot with no health left, which leaves a gap in the ring.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Orbit {
+    pub config: OrbitConfig,
+    pub angle: f32,
+    pub health: Vec<f32>,
+}
+
+impl Orbit {
+    pub fn new(config: OrbitConfig) -> Self {
+        Self {
+            config,
+            angle: 0.,
+            health: vec![config.health; config.count],
+        }
+    }
+
+    pub fn tick(&mut self, delta_time: f32) { self.angle = (self.angle + self.config.speed * delta_time) % TAU; }
+
+    /// Offsets of the blockers that are still intact from the centre of the orbit.
+    ///
+    pub fn offsets(&self, radius: f32) -> Vec<Vec2> {
+        self.health.iter()
+            .enumerate()
+            .filter(|(_, h)| **h > 0.)
+            .map(|(k, _)| Mat2::from_angle(self.angle + TAU * k as f32 / self.config.count as f32) * Vec2::X * radius * self.config.distance)
+            .collect()
+    }
+
+    /// Damages the `n`-th blocker that is still intact, in the order of `offsets`.
+    ///
+    pub fn damage(&mut self, n: usize, dmg: f32) {
+        if let Some(health) = self.health.iter_mut().filter(|h| **h > 0.).nth(n) {
+            *health -= dmg;
+        }
+    }
+}
+
+/// Sprites of an enemy, `Directional` holds the prefix of the `_north`, `_east`, `_south` and `_west` sprites.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Look {
+    Trembling(String),
+    Rotating(String),
+    Directional(String),
+    Shooting {
+        base: String,
+        cardinals: String,
+        diagonals: String,
+    },
+    Fly,
+    Charger,
+    Turret,
+    Burrower,
+    Nest,
+    Worm,
+    Warden,
+}
+
+/// A single enemy type for every enemy of the game, put together from behaviour components.
+///
 #[derive(Clone, Debug)]
-pub struct EnemyMask {
+pub struct Enemy {
     pub props: ActorProps,
+    pub speed: f32,
+    pub decay: f32,
+    pub acceleration: f32,
     pub state: ActorState,
     pub health: f32,
+    pub max_health: f32,
     pub damage: f32,
     pub shoot_rate: f32,
     pub shoot_range: f32,
     pub shoot_timeout: f32,
     pub animation_cooldown: f32,
     pub afterlock_cooldown: f32,
+    pub change_direction_cooldown: f32,
+    pub creep_cooldown: f32,
+    pub hazard_cooldown: f32,
+    /// Hazard damage taken since the last tick.
+    ///
+    pub hazard_damage: f32,
+    pub accuracy: Accuracy,
+    pub layer: MovementLayer,
+    pub movement: Movement,
+    pub attack: Attack,
+    pub on_hit: OnHit,
+    pub death: OnDeath,
+    pub leaves_creep: bool,
+    pub blocks_clear: bool,
+    /// Embedded enemies sit inside a wall that is placed under them when the room is generated.
+    ///
+    pub embedded: bool,
+    /// Anchored enemies stay where they are spawned, nothing can push them off their spot.
+    ///
+    pub anchored: bool,
+    pub spawner: Option<Spawner>,
+    /// Minions belong to the brood of the spawner that made them.
+    ///
+    pub brood: Option<Brood>,
+    /// Worms are spawned as a head, the body segments are laid out behind it by `grow_body`.
+    ///
+    pub worm: Option<WormConfig>,
+    pub segment: Option<Segment>,
+    pub orbit: Option<Orbit>,
+    pub brain: Option<Brain>,
+    pub evasion: Option<Evasion>,
+    pub look: Look,
+    /// Bosses are scaled by the boss difficulty and show a health bar.
+    ///
+    pub is_boss: bool,
+    pub death_sound: String,
 }
 
-impl Default for EnemyMask {
+impl Default for Enemy {
     fn default() -> Self {
         Self {
             props: ActorProps::default(),
+            speed: ENEMY_SPEED,
+            decay: 10.,
+            acceleration: 10.,
+            state: ActorState::Base,
             health: ENEMY_HEALTH,
+            max_health: ENEMY_HEALTH,
             damage: ENEMY_DAMAGE,
-            state: ActorState::Base,
             shoot_rate: ENEMY_SHOOT_RATE,
             shoot_range: ENEMY_SHOOT_RANGE,
             shoot_timeout: 0.,
             animation_cooldown: 0.,
             afterlock_cooldown: ENEMY_AFTERLOCK_COOLDOWN,
+            change_direction_cooldown: ENEMY_WANDERER_CHANGE_DIRECTION_COOLDOWN,
+            creep_cooldown: 0.,
+            hazard_cooldown: 0.,
+            hazard_damage: 0.,
+            accuracy: Accuracy { lead: 0., spread: 0. },
+            layer: MovementLayer::Ground,
+            movement: Movement::Still,
+            attack: Attack::None,
+            on_hit: OnHit::Flash,
+            death: OnDeath::Vanish,
+            leaves_creep: false,
+            blocks_clear: true,
+            embedded: false,
+            anchored: false,
+            spawner: None,
+            brood: None,
+            worm: None,
+            segment: None,
+            orbit: None,
+            brain: None,
+            evasion: None,
+            look: Look::Fly,
+            is_boss: false,
+            death_sound: "enemy_death_sound".to_string(),
         }
     }
 }
 
-impl Actor for EnemyMask {
-    fn update(&mut self, ctx: &mut Context, conf: &mut Config, _delta_time: f32) -> GameResult {
-        self.afterlock_cooldown = f32::max(0., self.afterlock_cooldown - _delta_time);
+impl Enemy {
+    fn is_attacking(&self) -> bool {
+        let surfaced = match self.movement {
+            Movement::Burrow(burrow) => burrow.is_surfaced(),
+            _ => true,
+        };
+        surfaced && self.brain.is_none_or(|b| b.state == AiState::Attack)
+    }
 
-        self.velocity_lerp(_delta_time, 0., 5., 0.);
-        self.props.pos.0 += self.props.velocity;
+    fn chase_move(&mut self, act: &ActContext) {
+        let ActContext { sw, sh, flow, obstacles, player, .. } = *act;
 
-        self.shoot_timeout = f32::max(0., self.shoot_timeout - _delta_time);
-        self.animation_cooldown = f32::max(0., self.animation_cooldown - _delta_time);
+        let brain = match self.brain {
+            Some(brain) => brain,
+            None => return self.chase(sw, sh, obstacles, flow, player),
+        };
 
-        if self.animation_cooldown == 0. { self.state = ActorState::Base; }
-        if self.health <= 0. {
-            conf.assets.audio.get_mut("enemy_death_sound").unwrap().play(ctx)?; 
-            self.state = ActorState::Dead;
+        match brain.destination(self.get_pos(), player.get_pos()) {
+            Some(_) if brain.state == AiState::Pursue => self.chase(sw, sh, obstacles, flow, player),
+            Some(dest) if dest.distance(self.get_pos()) > sw / ROOM_WIDTH as f32 * 0.5 => {
+                self.props.translation = (dest - self.get_pos()).normalize_or_zero();
+                self.props.forward = self.props.translation;
+            },
+            _ => self.props.translation = Vec2::ZERO,
         }
-
-        Ok(())
     }
 
-    fn draw(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
-        let (sw, sh) = (conf.screen_width, conf.screen_height);
+    /// Wanders around, or backs off in a straight line when the brain says to retreat and the way is free.
+    ///
+    fn wander_move(&mut self, act: &ActContext) {
+        let ActContext { sw, sh, flow, player, .. } = *act;
 
-        let tremble: f32 = (thread_rng().gen::<f32>() * 2. - 1.) * 0.1;
-        let sign = thread_rng().gen_range(-1..2) as f32;
-
-        let draw_params = DrawParam::default()
-            .dest(self.props.pos)
-            .scale(self.scale_to_screen(sw, sh, conf.assets.sprites.get("enemy_mask_base").unwrap().dimensions()))
-            .offset([0.5 + tremble, 0.5 + tremble * sign]);
-
-        match self.state {
-            ActorState::Damaged => graphics::draw(ctx, conf.assets.sprites.get("enemy_mask_base").unwrap(), draw_params.color(Color::RED))?,
-            _ => graphics::draw(ctx, conf.assets.sprites.get("enemy_mask_base").unwrap(), draw_params)?,
+        if self.brain.map(|b| b.state) != Some(AiState::Retreat) {
+            return self.wander(sw, sh, flow.get_grid());
         }
 
-        if conf.draw_bcircle_model { self.draw_bcircle(ctx, (sw, sh))?; }
+        let away = (self.get_pos() - player.get_pos()).normalize_or_zero();
+        let dir = match away.x.abs() > away.y.abs() {
+            true => Vec2::new(away.x.signum(), 0.),
+            false => Vec2::new(0., away.y.signum()),
+        };
+        let (i, j) = pos_to_room_coords(self.get_pos() + dir * sw / ROOM_WIDTH as f32, sw, sh);
 
-        Ok(())
+        if i < ROOM_HEIGHT && j < ROOM_WIDTH && flow.get_grid()[i][j] >= 0 {
+            self.props.translation = dir;
+            self.props.forward = dir;
+        }
+        else { self.wander(sw, sh, flow.get_grid()); }
     }
 
-    fn get_pos(&self) -> Vec2 { self.props.pos.into() }
-
-    fn get_scale(&self) -> Vec2 { self.props.scale }
-
-    fn get_velocity(&self) -> Vec2 { self.props.velocity }
+    /// Heads straight for the player when it is in sight and follows the flow field around walls otherwise.
+    ///
+    fn flock_move(&mut self, act: &ActContext, config: &FlockConfig) {
+        let ActContext { sw, sh, flow, obstacles, allies, player } = *act;
 
-    fn get_translation(&self) -> Vec2 { self.props.translation }
-
-    fn get_forward(&self) -> Vec2 { self.props.forward }
-
-    fn set_pos(&mut self, new_pos: Vec2) { self.props.pos = new_pos.into(); }
-
-    fn set_scale(&mut self, new_scale: Vec2) { self.props.scale = new_scale; }
+        let attraction = match has_line_of_sight(self.get_pos(), player.get_pos(), obstacles, sw, sh) {
+            true => player.get_pos() - self.get_pos(),
+            false => flow.steer(self.get_pos(), sw, sh),
+        };
+        let neighbours = allies.nearby(self.get_pos(), config.radius);
 
-    fn set_velocity(&mut self, new_velocity: Vec2) { self.props.velocity = new_velocity; } 
+        self.props.translation = flock(self.get_pos(), &neighbours, attraction, config);
+        self.props.forward = self.props.translation;
+    }
 
-    fn set_translation(&mut self, new_translation: Vec2) { self.props.translation = new_translation; }
+    /// Patrols until the player lines up with it, then winds up and dashes until it slams into a wall.
+    ///
+    fn charge_move(&mut self, act: &ActContext) {
+        let ActContext { sw, sh, flow, obstacles, player, .. } = *act;
+        let radius = self.get_bcircle(sw, sh).1;
+
+        let (dir, facing, stunned) = match &mut self.movement {
+            Movement::Charge(charge) => {
+                let was_dashing = charge.is_dashing();
+                let dir = charge.charge(self.props.pos.0, player.get_pos(), radius, obstacles, sw, sh);
+                (dir, charge.dir(), was_dashing && charge.is_stunned())
+            },
+            _ => return,
+        };
 
-    fn set_forward(&mut self, new_forward: Vec2) { self.props.forward = new_forward; } 
+        match dir {
+            Some(dir) => self.props.translation = dir,
+            None => self.wander(sw, sh, flow.get_grid()),
+        }
+        if let Some(facing) = facing { self.props.forward = facing; }
+        if stunned { self.props.velocity = Vec2::ZERO; }
+    }
 
-    fn get_health(&self) -> f32 { self.health }
+    fn shoot_aimed(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, shots: &mut Vec<Shot>, player: &Player) {
+        if self.get_pos().distance(player.get_pos()) > self.shoot_range * 0.8
+            || !has_line_of_sight(self.get_pos(), player.get_pos(), obstacles, sw, sh) {
+            return;
+        }
 
-    fn get_state(&self) -> ActorState { self.state }
+        self.props.forward = aim(self.get_pos(), player.get_pos(), player.get_velocity(), SHOT_SPEED, &self.accuracy);
+        self.state = ActorState::Shoot;
+        self.shoot_timeout = 1. / self.shoot_rate;
+        self.animation_cooldown = ANIMATION_COOLDOWN / self.shoot_rate;
 
-    fn damage(&mut self, dmg: f32) { 
-        self.health -= dmg; 
-        self.state = ActorState::Damaged;
-        self.animation_cooldown = ANIMATION_COOLDOWN;
+        shots.push(self.new_shot(self.props.forward.normalize()));
     }
 
-    fn get_damage(&self) -> f32 { return self.damage }
+    fn shoot_cross(&mut self, shots: &mut Vec<Shot>) {
+        self.props.forward = match thread_rng().gen_bool(0.5) {
+            true => Vec2::X,
+            false => Vec2::Y,
+        };
+        self.state = ActorState::Shoot;
+        self.shoot_timeout = 1. / self.shoot_rate;
+        self.animation_cooldown = ANIMATION_COOLDOWN / self.shoot_rate;
 
-    fn get_tag(&self) -> ActorTag { ActorTag::Enemy }
+        let mut shot_dir = match self.props.forward.x as i32 {
+            1 => Vec2::X,
+            _ => Vec2::ONE,
+        }.normalize();
 
-    fn act(&mut self, sw: f32, sh: f32, _grid: &[[i32; ROOM_WIDTH]], _obstacles: &Vec<Box<dyn Stationary>>, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult {
-        if self.afterlock_cooldown == 0. {
-            self.shoot(sw, sh, _obstacles, _shots, _player);
+        for _ in 0..4 {
+            shots.push(self.new_shot(shot_dir));
+            shot_dir = shot_dir.perp();
         }
-        Ok(())
     }
 
-    fn as_any(&self) -> &dyn Any { self }
-
-    fn as_any_mut(&mut self) -> &mut dyn Any { self }
-}
-
-impl Shooter for EnemyMask {
-    fn shoot(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, shots: &mut Vec<Shot>, player: &Player) {
-        let (mut cp, mut cn) = (Vec2::ZERO, Vec2::ZERO);
-        let mut ct = 0.;
-
-        if self.shoot_timeout != 0. 
-            || self.afterlock_cooldown != 0. 
-            || self.get_pos().distance(player.get_pos()) > self.shoot_range * 0.8
-            || obstacles.iter()
-                .filter(|o| { ray_vs_rect(&self.get_pos(), &(player.get_pos() - self.get_pos()), &o.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct) && ct < 1. })
-                .count() != 0 {
-            return;
-        }
+    fn shoot_volley(&mut self, sw: f32, sh: f32, shots: &mut Vec<Shot>, config: &VolleyConfig) {
+        let tile = Vec2::new(sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32);
+        let base = match self.props.forward.try_normalize() {
+            Some(forward) => forward,
+            None => {
+                let inward = Vec2::new(sw, sh) / 2. - self.get_pos();
+                match inward.x.abs() > inward.y.abs() {
+                    true => Vec2::new(inward.x.signum(), 0.),
+                    false => Vec2::new(0., inward.y.signum()),
+                }
+            },
+        };
+        let muzzle = match self.embedded {
+            true => tile.min_element(),
+            false => 0.,
+        };
 
-        self.props.forward = player.get_pos() - self.get_pos();
         self.state = ActorState::Shoot;
         self.shoot_timeout = 1. / self.shoot_rate;
         self.animation_cooldown = ANIMATION_COOLDOWN / self.shoot_rate;
 
-        let shot_dir = self.props.forward.normalize();
+        for k in 0..config.count {
+            let angle = match (config.count, config.spread >= TAU) {
+                (1, _) => 0.,
+                (n, true) => config.spread * k as f32 / n as f32,
+                (n, false) => config.spread * (k as f32 / (n - 1) as f32 - 0.5),
+            };
+            let dir = Mat2::from_angle(angle) * base;
+            let mut shot = self.new_shot(dir);
+            shot.props.pos = (self.get_pos() + dir * muzzle).into();
+            shot.spawn_pos = shot.props.pos;
+            shots.push(shot);
+        }
+
+        self.props.forward = Mat2::from_angle(config.spin) * base;
+    }
 
-        let shot = Shot {
+    fn new_shot(&self, dir: Vec2) -> Shot {
+        Shot {
             props: ActorProps {
                 pos: self.props.pos,
                 scale: Vec2::splat(SHOT_SCALE),
-                translation: shot_dir,
-                forward: shot_dir,
+                translation: dir,
+                forward: dir,
                 velocity: Vec2::ZERO,
+                surface: Surface::Normal,
+                slow: 1.,
             },
             spawn_pos: self.props.pos,
             speed: SHOT_SPEED,
             range: self.shoot_range,
             damage: self.damage,
             tag: ShotTag::Enemy,
-        };
-
-        shots.push(shot);
-    }
-
-    fn get_range(&self) -> f32 { self.shoot_range }
-
-    fn get_rate(&self) -> f32 { self.shoot_rate }
-}
-
-#[derive(Clone, Debug)]
-pub struct EnemyBlueGuy {
-    pub props: ActorProps,
-    pub speed: f32,
-    pub state: ActorState,
-    pub health: f32,
-    pub damage: f32,
-    pub animation_cooldown: f32,
-    pub afterlock_cooldown: f32,
-}
-
-impl Default for EnemyBlueGuy {
-    fn default() -> Self {
-        Self {
-            props: ActorProps::default(),
-            speed: ENEMY_SPEED,
-            health: ENEMY_HEALTH,
-            damage: ENEMY_DAMAGE,
-            state: ActorState::Base,
-            animation_cooldown: 0.,
-            afterlock_cooldown: ENEMY_AFTERLOCK_COOLDOWN,
         }
     }
-}
 
-impl Actor for EnemyBlueGuy {
-    fn update(&mut self, ctx: &mut Context, conf: &mut Config, _delta_time: f32) -> GameResult {
-        self.afterlock_cooldown = f32::max(0., self.afterlock_cooldown - _delta_time);
-        
-        self.velocity_lerp(_delta_time, self.speed, 10., 20.);
-        self.props.pos.0 += self.props.velocity;
+    fn draw_sprite(&self, ctx: &mut Context, conf: &mut Config, name: &str, rotation: f32, offset: [f32; 2]) -> GameResult {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let sprite = conf.assets.sprites.get(name).unwrap();
 
-        self.animation_cooldown = f32::max(0., self.animation_cooldown - _delta_time);
+        let draw_params = DrawParam::default()
+            .dest(self.props.pos)
+            .scale(self.scale_to_screen(sw, sh, sprite.dimensions()))
+            .rotation(rotation)
+            .offset(offset);
 
-        if self.animation_cooldown == 0. { self.state = ActorState::Base; }
-        if self.health <= 0. {
-            conf.assets.audio.get_mut("enemy_death_sound").unwrap().play(ctx)?; 
-            self.state = ActorState::Dead;
+        match self.state {
+            ActorState::Damaged => graphics::draw(ctx, sprite, draw_params.color(Color::RED))?,
+            _ => graphics::draw(ctx, sprite, draw_params)?,
         }
 
         Ok(())
     }
 
-    fn draw(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
-        let (sw, sh) = (conf.screen_width, conf.screen_height);
-        let mut angle = self.props.forward.angle_between(Vec2::Y + self.props.pos.0 - self.props.pos.0);
-        if angle.is_nan() { angle = 0.; }
+    fn draw_fly(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let flap = thread_rng().gen_range(0.6..1.);
+        let body = match self.state {
+            ActorState::Damaged => Color::RED,
+            _ => Color::new(0.1, 0.1, 0.1, 1.),
+        };
 
-        let draw_params = DrawParam::default()
-            .dest(self.props.pos)
-            .scale(self.scale_to_screen(sw, sh, conf.assets.sprites.get("enemy_blue_guy_base").unwrap().dimensions()))
-            .rotation(-angle)
-            .offset([0.5, 0.5]);
+        let mesh = MeshBuilder::new()
+            .ellipse(DrawMode::fill(), [pos.0.x - r * 0.6, pos.0.y - r * 0.3], r * 0.6 * flap, r * 0.35, 0.5, Color::new(0.9, 0.9, 1., 0.6))?
+            .ellipse(DrawMode::fill(), [pos.0.x + r * 0.6, pos.0.y - r * 0.3], r * 0.6 * flap, r * 0.35, 0.5, Color::new(0.9, 0.9, 1., 0.6))?
+            .circle(DrawMode::fill(), pos, r * 0.5, 0.5, body)?
+            .build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-        match self.state {
-            ActorState::Damaged => graphics::draw(ctx, conf.assets.sprites.get("enemy_blue_guy_base").unwrap(), draw_params.color(Color::RED))?,
-            _ => graphics::draw(ctx, conf.assets.sprites.get("enemy_blue_guy_base").unwrap(), draw_params)?,
-        }
+        Ok(())
+    }
 
-        if conf.draw_bcircle_model { self.draw_bcircle(ctx, (sw, sh))?; }
+    /// Horned body that glows while winding up and turns grey while stunned.
+    ///
+    fn draw_charger(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let charge = match self.movement {
+            Movement::Charge(charge) => Some(charge),
+            _ => None,
+        };
+        let body = match (self.state, charge.map(|c| c.phase)) {
+            (ActorState::Damaged, _) => Color::RED,
+            (_, Some(ChargePhase::Windup { .. })) => Color::new(1., 0.6, 0.2, 1.),
+            (_, Some(ChargePhase::Stunned { .. })) => Color::new(0.5, 0.5, 0.5, 1.),
+            _ => Color::new(0.6, 0.25, 0.1, 1.),
+        };
+        let forward = self.props.forward.try_normalize().unwrap_or(Vec2::Y);
+        let (tip, side) = (pos.0 + forward * r, forward.perp() * r * 0.5);
+
+        let mesh = MeshBuilder::new()
+            .polygon(DrawMode::fill(), &[tip + side * 0.4, pos.0 + side, pos.0 + side * 0.5], Color::new(0.9, 0.9, 0.8, 1.))?
+            .polygon(DrawMode::fill(), &[tip - side * 0.4, pos.0 - side, pos.0 - side * 0.5], Color::new(0.9, 0.9, 0.8, 1.))?
+            .circle(DrawMode::fill(), pos, r * 0.75, 0.5, body)?
+            .build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
         Ok(())
     }
 
-    fn get_pos(&self) -> Vec2 { self.props.pos.into() }
+    /// Armoured dome with a barrel along the direction of the next volley, it goes dark once shut off.
+    ///
+    fn draw_turret(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let body = match (self.state, self.attack, self.on_hit) {
+            (ActorState::Damaged, _, _) => Color::RED,
+            (_, Attack::None, _) => Color::new(0.2, 0.2, 0.2, 1.),
+            (ActorState::Shoot, _, _) => Color::new(0.9, 0.7, 0.3, 1.),
+            (_, _, OnHit::Ignore) => Color::new(0.45, 0.45, 0.5, 1.),
+            _ => Color::new(0.55, 0.35, 0.3, 1.),
+        };
+        let barrel = self.props.forward.try_normalize().unwrap_or(Vec2::ZERO) * r;
 
-    fn get_scale(&self) -> Vec2 { self.props.scale }
+        let mesh = MeshBuilder::new()
+            .line(&[pos.0, pos.0 + barrel], r * 0.3, Color::new(0.15, 0.15, 0.15, 1.))?
+            .circle(DrawMode::fill(), pos, r * 0.6, 0.5, body)?
+            .circle(DrawMode::stroke(2.), pos, r * 0.6, 0.5, Color::BLACK)?
+            .build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-    fn get_velocity(&self) -> Vec2 { self.props.velocity }
+        Ok(())
+    }
 
-    fn get_translation(&self) -> Vec2 { self.props.translation }
+    /// Nothing shows while it digs, a shaking mound telegraphs where it comes up.
+    ///
+    fn draw_burrower(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let phase = match self.movement {
+            Movement::Burrow(burrow) => burrow.phase,
+            _ => BurrowPhase::Surfaced,
+        };
+        if phase == BurrowPhase::Underground { return Ok(()); }
 
-    fn get_forward(&self) -> Vec2 { self.props.forward }
+        let shake = match phase {
+            BurrowPhase::Surfacing => Vec2::new(thread_rng().gen_range(-1. ..1.), thread_rng().gen_range(-1. ..1.)) * r * 0.1,
+            _ => Vec2::ZERO,
+        };
+        let body = match self.state {
+            ActorState::Damaged => Color::RED,
+            _ => Color::new(0.35, 0.2, 0.4, 1.),
+        };
 
-    fn set_pos(&mut self, new_pos: Vec2) { self.props.pos = new_pos.into(); }
+        let mut mesh = MeshBuilder::new();
+        mesh.ellipse(DrawMode::fill(), pos.0 + shake + Vec2::Y * r * 0.5, r, r * 0.4, 0.5, Color::new(0.4, 0.28, 0.15, 1.))?;
+        if phase == BurrowPhase::Surfaced {
+            mesh.circle(DrawMode::fill(), pos, r * 0.7, 0.5, body)?;
+            mesh.circle(DrawMode::fill(), pos.0 + Vec2::new(-0.25, -0.2) * r, r * 0.12, 0.5, Color::WHITE)?;
+            mesh.circle(DrawMode::fill(), pos.0 + Vec2::new(0.25, -0.2) * r, r * 0.12, 0.5, Color::WHITE)?;
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-    fn set_scale(&mut self, new_scale: Vec2) { self.props.scale = new_scale; }
+        Ok(())
+    }
 
-    fn set_velocity(&mut self, new_velocity: Vec2) { self.props.velocity = new_velocity; } 
+    /// Lumpy mound with a few holes, it swells up right before a minion crawls out.
+    ///
+    fn draw_nest(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let swell = match &self.spawner {
+            Some(spawner) if spawner.timer < 0.5 => 1. + (0.5 - spawner.timer) * 0.2,
+            _ => 1.,
+        };
+        let body = match self.state {
+            ActorState::Damaged => Color::RED,
+            _ => Color::new(0.45, 0.35, 0.2, 1.),
+        };
 
-    fn set_translation(&mut self, new_translation: Vec2) { self.props.translation = new_translation; }
+        let mut mesh = MeshBuilder::new();
+        mesh.ellipse(DrawMode::fill(), pos, r * swell, r * 0.75 * swell, 0.5, body)?;
+        for hole in [Vec2::new(-0.4, -0.2), Vec2::new(0.35, -0.3), Vec2::new(0., 0.3)] {
+            mesh.circle(DrawMode::fill(), pos.0 + hole * r, r * 0.15, 0.5, Color::new(0.1, 0.05, 0.05, 1.))?;
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-    fn set_forward(&mut self, new_forward: Vec2) { self.props.forward = new_forward; } 
+        Ok(())
+    }
 
-    fn get_health(&self) -> f32 { self.health }
+    /// Ringed body segments, the head has a pair of eyes looking where it goes.
+    ///
+    fn draw_worm(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let body = match self.state {
+            ActorState::Damaged => Color::RED,
+            _ => Color::new(0.8, 0.5, 0.55, 1.),
+        };
 
-    fn get_state(&self) -> ActorState { self.state }
+        let mut mesh = MeshBuilder::new();
+        mesh.circle(DrawMode::fill(), pos, r * 0.9, 0.5, body)?;
+        mesh.circle(DrawMode::stroke(2.), pos, r * 0.6, 0.5, Color::new(0.55, 0.3, 0.35, 1.))?;
+        if self.segment.as_ref().is_none_or(|s| s.is_head()) {
+            let forward = self.props.forward.try_normalize().unwrap_or(Vec2::Y);
+            for side in [-1., 1.] {
+                mesh.circle(DrawMode::fill(), pos.0 + (forward * 0.45 + forward.perp() * side * 0.35) * r, r * 0.15, 0.5, Color::BLACK)?;
+            }
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-    fn damage(&mut self, dmg: f32) { 
-        self.health -= dmg; 
-        self.state = ActorState::Damaged;
-        self.animation_cooldown = ANIMATION_COOLDOWN;
+        Ok(())
     }
 
-    fn get_damage(&self) -> f32 { return self.damage }
+    /// Pale core with a dark pupil that follows the player.
+    ///
+    fn draw_warden(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let (pos, r) = self.get_bcircle(sw, sh);
+        let body = match self.state {
+            ActorState::Damaged => Color::RED,
+            _ => Color::new(0.85, 0.85, 0.7, 1.),
+        };
+        let look = self.props.forward.try_normalize().unwrap_or(Vec2::ZERO) * r * 0.3;
 
-    fn get_tag(&self) -> ActorTag { ActorTag::Enemy }
+        let mesh = MeshBuilder::new()
+            .circle(DrawMode::fill(), pos, r * 0.8, 0.5, body)?
+            .circle(DrawMode::fill(), pos.0 + look, r * 0.3, 0.5, Color::new(0.2, 0.1, 0.3, 1.))?
+            .build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-    fn act(&mut self, sw: f32, sh: f32, _grid: &[[i32; ROOM_WIDTH]], _obstacles: &Vec<Box<dyn Stationary>>, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult { 
-        if self.afterlock_cooldown == 0. {
-            self.chase(sw, sh, _obstacles, _grid, _player);
-        }
         Ok(())
     }
 
-    fn as_any(&self) -> &dyn Any { self }
+    /// Blockers fade as they take damage, enemies without an orbit draw nothing.
+    ///
+    fn draw_orbit(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let orbit = match &self.orbit {
+            Some(orbit) => orbit,
+            None => return Ok(()),
+        };
+        let health = orbit.health.iter().filter(|h| **h > 0.);
+        let shields = self.get_shields(sw, sh);
+        if shields.is_empty() { return Ok(()); }
+
+        let mut mesh = MeshBuilder::new();
+        for (((pos, r), _), h) in shields.into_iter().zip(health) {
+            let alpha = 0.4 + 0.6 * h / orbit.config.health;
+            mesh.circle(DrawMode::fill(), pos, r, 0.5, Color::new(0.5, 0.7, 0.9, alpha))?;
+            mesh.circle(DrawMode::stroke(2.), pos, r, 0.5, Color::new(0.2, 0.3, 0.5, 1.))?;
+        }
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
 
-    fn as_any_mut(&mut self) -> &mut dyn Any { self }
-}
+        Ok(())
+    }
 
-impl Chaser for EnemyBlueGuy {
-    fn chase(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, grid: &[[i32; ROOM_WIDTH]], player: &Player) {
-        if self.afterlock_cooldown == 0. {
-            let (mut cp, mut cn) = (Vec2::ZERO, Vec2::ZERO);
-            let mut ct = 0.;
-            let mut target = player.get_pos();
+    /// Makes the head of a worm and lays its body out behind it, along a path of free tiles that heads for the middle of the room.
+    /// Segments that don't fit on the path are stacked at its end. Anything that isn't an unsegmented worm grows nothing.
+    ///
+    pub fn grow_body(&mut self, grid: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], sw: f32, sh: f32) -> Vec<Enemy> {
+        let config = match (self.worm, &self.segment) {
+            (Some(config), None) => config,
+            _ => return Vec::new(),
+        };
+        let gap = self.get_bcircle(sw, sh).1 * config.spacing;
+        let length = gap * (config.segments - 1) as f32;
+
+        let mut path = vec![self.get_pos()];
+        let mut visited = vec![pos_to_room_coords(self.get_pos(), sw, sh)];
+        let mut dir = (Vec2::new(sw, sh) / 2. - self.get_pos()).try_normalize().unwrap_or(Vec2::X);
+        let mut walked = 0.;
+
+        while walked < length {
+            let (i, j) = visited[visited.len() - 1];
+            let last = path[path.len() - 1];
+            let next = [(0, 1), (1, 0), (0, -1), (-1, 0)].iter()
+                .map(|(di, dj)| ((i as i32 + di) as usize, (j as i32 + dj) as usize))
+                .filter(|&(ni, nj)| ni < ROOM_HEIGHT && nj < ROOM_WIDTH && grid[ni][nj] >= 0 && !visited.contains(&(ni, nj)))
+                .map(|(ni, nj)| (ni, nj, room_coords_to_pos(ni, nj, sw, sh)))
+                .max_by(|a, b| (a.2 - last).dot(dir).total_cmp(&(b.2 - last).dot(dir)));
+            let (ni, nj, pos) = match next {
+                Some(next) => next,
+                None => break,
+            };
 
-            if obstacles.iter()
-                .filter(|o| { ray_vs_rect(&self.get_pos(), &(player.get_pos() - self.get_pos()), &o.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct) && ct < 1. })
-                .count() != 0 {
-                target = self.find_path(grid, sw, sh);
-            }
+            dir = (pos - last).normalize_or_zero();
+            walked += pos.distance(last);
+            path.push(pos);
+            visited.push((ni, nj));
+        }
 
-            self.props.translation = (target - self.get_pos()).normalize_or_zero();
-            self.props.forward = self.props.translation;
+        let points = (0..config.segments)
+            .filter_map(|k| point_along(path.iter(), gap * k as f32))
+            .collect::<Vec<_>>();
+        let trail = |k: usize| &points[k..usize::min(k + 2, points.len())];
+        let mut pool = None;
+        if config.shared {
+            self.health *= config.segments as f32;
+            self.max_health *= config.segments as f32;
+            pool = Some(Rc::new(Cell::new(self.health)));
         }
+
+        let head = Segment::new(0, None, trail(0), config.spacing, pool.clone());
+        let mut leader = Rc::downgrade(&head.trail);
+        self.segment = Some(head);
+
+        (1..config.segments)
+            .map(|k| {
+                let segment = Segment::new(k, Some(leader.clone()), trail(k), config.spacing, pool.clone());
+                leader = Rc::downgrade(&segment.trail);
+                Enemy {
+                    props: ActorProps {
+                        pos: points[k].into(),
+                        forward: (points[k - 1] - points[k]).try_normalize().unwrap_or(self.props.forward),
+                        ..self.props
+                    },
+                    segment: Some(segment),
+                    ..self.clone()
+                }
+            })
+            .collect()
     }
-}
 
-#[derive(Clone, Debug, Copy)]
-pub struct EnemySlime {
-    pub props: ActorProps,
-    pub speed: f32,
-    pub state: ActorState,
-    pub health: f32,
-    pub damage: f32,
-    pub animation_cooldown: f32,
-    pub afterlock_cooldown: f32,
-    pub change_direction_cooldown: f32,
-}
+    fn draw_health_bar(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
+        let bbox = self.get_bbox(sw, sh);
+        let (hbw, hbh) = (bbox.w * 1.4, sh * 0.01);
+        let (hbx, hby) = (bbox.x - (hbw - bbox.w) * 0.5, bbox.y - hbh * 2.);
 
-impl Default for EnemySlime {
-    fn default() -> Self {
-        Self {
-            props: ActorProps::default(),
-            speed: ENEMY_SPEED * 0.5,
-            health: ENEMY_HEALTH * 1.5,
-            damage: ENEMY_DAMAGE,
-            state: ActorState::Base,
-            animation_cooldown: 0.,
-            afterlock_cooldown: ENEMY_AFTERLOCK_COOLDOWN,
-            change_direction_cooldown: ENEMY_WANDERER_CHANGE_DIRECTION_COOLDOWN,
-        }
+        let bar = MeshBuilder::new()
+            .rectangle(DrawMode::fill(), Rect::new(hbx, hby, hbw / self.max_health * self.health, hbh), Color::RED)?
+            .rectangle(DrawMode::stroke(3.), Rect::new(hbx, hby, hbw, hbh), Color::BLACK)?
+            .build(ctx)?;
+
+        graphics::draw(ctx, &bar, DrawParam::default())?;
+
+        Ok(())
     }
 }
-    
-impl Actor for EnemySlime {
+
+impl Actor for Enemy {
     fn update(&mut self, ctx: &mut Context, conf: &mut Config, _delta_time: f32) -> GameResult {
         self.afterlock_cooldown = f32::max(0., self.afterlock_cooldown - _delta_time);
         self.change_direction_cooldown = f32::max(0., self.change_direction_cooldown - _delta_time);
+        self.creep_cooldown = f32::max(0., self.creep_cooldown - _delta_time);
+        self.hazard_cooldown = f32::max(0., self.hazard_cooldown - _delta_time);
+        self.shoot_timeout = f32::max(0., self.shoot_timeout - _delta_time);
+        if let Some(brain) = &mut self.brain { brain.tick(_delta_time); }
+        if let Some(evasion) = &mut self.evasion { evasion.tick(_delta_time); }
+        match &mut self.movement {
+            Movement::Charge(charge) => charge.tick(_delta_time),
+            Movement::Burrow(burrow) => burrow.tick(_delta_time),
+            _ => (),
+        }
+        if let Some(spawner) = &mut self.spawner { spawner.tick(_delta_time); }
+        if let Some(orbit) = &mut self.orbit { orbit.tick(_delta_time); }
+
+        let speed = match self.movement {
+            Movement::Charge(charge) if charge.is_dashing() => self.speed * charge.config.dash_speed,
+            _ => self.speed,
+        };
+        self.velocity_lerp(_delta_time, speed, self.decay, self.acceleration);
+        match self.anchored {
+            true => self.props.velocity = Vec2::ZERO,
+            false => self.props.pos.0 += self.props.velocity,
+        }
 
-        self.velocity_lerp(_delta_time, self.speed, 10., 10.);
-        self.props.pos.0 += self.props.velocity;
+        if let Some(segment) = &self.segment {
+            let gap = self.get_bcircle(conf.screen_width, conf.screen_height).1 * segment.spacing;
+            if let Some(pos) = segment.follow(gap) {
+                self.props.forward = (pos - self.get_pos()).try_normalize().unwrap_or(self.props.forward);
+                self.props.pos = pos.into();
+                self.props.velocity = Vec2::ZERO;
+            }
+            segment.record(self.get_pos(), gap);
+        }
 
         self.animation_cooldown = f32::max(0., self.animation_cooldown - _delta_time);
 
         if self.animation_cooldown == 0. { self.state = ActorState::Base; }
+        if self.hazard_cooldown == 0. && self.hazard_damage > 0. {
+            self.damage(self.hazard_damage);
+            self.hazard_damage = 0.;
+            self.hazard_cooldown = HAZARD_TICK;
+        }
         if self.health <= 0. {
-            conf.assets.audio.get_mut("enemy_death_sound").unwrap().play(ctx)?; 
+            conf.assets.audio.get_mut(&self.death_sound).unwrap().play(ctx)?;
             self.state = ActorState::Dead;
         }
 
@@ -354,28 +925,58 @@ impl Actor for EnemySlime {
     fn draw(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
         let (sw, sh) = (conf.screen_width, conf.screen_height);
 
-        let sprite = match self.props.forward {
-            v if v == -Vec2::Y => conf.assets.sprites.get("enemy_slime_north").unwrap(),
-            v if v == Vec2::X => conf.assets.sprites.get("enemy_slime_east").unwrap(),
-            v if v == -Vec2::X => conf.assets.sprites.get("enemy_slime_west").unwrap(),
-            _ => conf.assets.sprites.get("enemy_slime_south").unwrap(),
-        };
+        match &self.look {
+            Look::Trembling(sprite) => {
+                let tremble: f32 = (thread_rng().gen::<f32>() * 2. - 1.) * 0.1;
+                let sign = thread_rng().gen_range(-1..2) as f32;
+                self.draw_sprite(ctx, conf, sprite, 0., [0.5 + tremble, 0.5 + tremble * sign])?;
+            },
+            Look::Rotating(sprite) => {
+                let mut angle = self.props.forward.angle_between(Vec2::Y + self.props.pos.0 - self.props.pos.0);
+                if angle.is_nan() { angle = 0.; }
+                self.draw_sprite(ctx, conf, sprite, -angle, [0.5, 0.5])?;
+            },
+            Look::Directional(prefix) => {
+                let dir = match self.props.forward {
+                    v if v == -Vec2::Y => "north",
+                    v if v == Vec2::X => "east",
+                    v if v == -Vec2::X => "west",
+                    _ => "south",
+                };
+                self.draw_sprite(ctx, conf, &format!("{}_{}", prefix, dir), 0., [0.5, 0.5])?;
+            },
+            Look::Shooting { base, cardinals, diagonals } => {
+                let sprite = match self.state {
+                    ActorState::Shoot => match self.props.forward.x as i32 {
+                        1 => cardinals,
+                        _ => diagonals,
+                    },
+                    _ => base,
+                };
+                self.draw_sprite(ctx, conf, sprite, 0., [0.5, 0.5])?;
+            },
+            Look::Fly => self.draw_fly(ctx, sw, sh)?,
+            Look::Charger => self.draw_charger(ctx, sw, sh)?,
+            Look::Turret => self.draw_turret(ctx, sw, sh)?,
+            Look::Burrower => self.draw_burrower(ctx, sw, sh)?,
+            Look::Nest => self.draw_nest(ctx, sw, sh)?,
+            Look::Worm => self.draw_worm(ctx, sw, sh)?,
+            Look::Warden => self.draw_warden(ctx, sw, sh)?,
+        }
 
-        let draw_params = DrawParam::default()
-            .dest(self.props.pos)
-            .scale(self.scale_to_screen(sw, sh, sprite.dimensions()))
-            .offset([0.5, 0.5]);
+        self.draw_orbit(ctx, sw, sh)?;
 
-        match self.state {
-            ActorState::Damaged => graphics::draw(ctx, sprite, draw_params.color(Color::RED))?,
-            _ => graphics::draw(ctx, sprite, draw_params)?,
-        }
+        if self.is_boss { self.draw_health_bar(ctx, sw, sh)?; }
 
         if conf.draw_bcircle_model { self.draw_bcircle(ctx, (sw, sh))?; }
 
         Ok(())
     }
 
+    fn get_props(&self) -> &ActorProps { &self.props }
+
+    fn get_props_mut(&mut self) -> &mut ActorProps { &mut self.props }
+
     fn get_pos(&self) -> Vec2 { self.props.pos.into() }
 
     fn get_scale(&self) -> Vec2 { self.props.scale }
@@ -390,168 +991,198 @@ impl Actor for EnemySlime {
 
     fn set_scale(&mut self, new_scale: Vec2) { self.props.scale = new_scale; }
 
-    fn set_velocity(&mut self, new_velocity: Vec2) { self.props.velocity = new_velocity; } 
+    fn set_velocity(&mut self, new_velocity: Vec2) { self.props.velocity = new_velocity; }
 
     fn set_translation(&mut self, new_translation: Vec2) { self.props.translation = new_translation; }
 
-    fn set_forward(&mut self, new_forward: Vec2) { self.props.forward = new_forward; } 
+    fn set_forward(&mut self, new_forward: Vec2) { self.props.forward = new_forward; }
 
     fn get_health(&self) -> f32 { self.health }
 
     fn get_state(&self) -> ActorState { self.state }
 
-    fn damage(&mut self, dmg: f32) { 
-        self.health -= dmg; 
-        self.state = ActorState::Damaged;
-        self.animation_cooldown = ANIMATION_COOLDOWN;
-    }
+    fn damage(&mut self, dmg: f32) {
+        if !self.is_hittable() { return; }
 
-    fn get_damage(&self) -> f32 { return self.damage }
-
-    fn get_tag(&self) -> ActorTag { ActorTag::Enemy }
-
-    fn act(&mut self, sw: f32, sh: f32, _grid: &[[i32; ROOM_WIDTH]], _obstacles: &Vec<Box<dyn Stationary>>, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult {
-        if self.afterlock_cooldown == 0. {
-            self.wander(sw, sh, _grid);
+        match self.on_hit {
+            OnHit::Flash => {
+                self.health -= dmg;
+                if let Some(pool) = self.segment.as_ref().and_then(|s| s.pool.as_ref()) {
+                    pool.set(pool.get() - dmg);
+                    self.health = pool.get();
+                }
+                self.state = ActorState::Damaged;
+                self.animation_cooldown = ANIMATION_COOLDOWN;
+            },
+            OnHit::Ignore => (),
         }
-        Ok(())
     }
 
-    fn as_any(&self) -> &dyn Any { self }
+    fn damage_over_time(&mut self, dmg: f32) {
+        if self.is_hittable() { self.hazard_damage += dmg; }
+    }
 
-    fn as_any_mut(&mut self) -> &mut dyn Any { self }
-}
+    fn get_damage(&self) -> f32 { self.damage }
 
-impl Wanderer for EnemySlime {
-    fn get_change_direction_cooldown(&self) -> f32 { self.change_direction_cooldown }
+    fn get_tag(&self) -> ActorTag { ActorTag::Enemy }
 
-    fn set_change_direction_cooldown(&mut self, cd: f32) { self.change_direction_cooldown = cd; }
-}
+    fn get_layer(&self) -> MovementLayer { self.layer }
 
-#[derive(Clone, Debug, Copy)]
-pub struct BossWeirdBall {
-    pub props: ActorProps,
-    pub speed: f32,
-    pub state: ActorState,
-    pub health: f32,
-    pub max_health: f32,
-    pub damage: f32,
-    pub shoot_rate: f32,
-    pub shoot_range: f32,
-    pub shoot_timeout: f32,
-    pub animation_cooldown: f32,
-    pub afterlock_cooldown: f32,
-    pub change_direction_cooldown: f32,
-}
-
-impl Default for BossWeirdBall {
-    fn default() -> Self {
-        Self {
-            props: ActorProps::default(),
-            speed: ENEMY_SPEED * 0.5,
-            health: BOSS_HEALTH,
-            max_health: BOSS_HEALTH,
-            damage: ENEMY_DAMAGE * 2.,
-            shoot_rate: ENEMY_SHOOT_RATE,
-            shoot_range: ENEMY_SHOOT_RANGE,
-            shoot_timeout: 0.,
-            state: ActorState::Base,
-            animation_cooldown: 0.,
-            afterlock_cooldown: ENEMY_AFTERLOCK_COOLDOWN,
-            change_direction_cooldown: ENEMY_WANDERER_CHANGE_DIRECTION_COOLDOWN,
-        }
-    }
-}
+    fn flocks(&self) -> bool { matches!(self.movement, Movement::Flock(_)) }
 
-impl Actor for BossWeirdBall {
-    fn update(&mut self, ctx: &mut Context, conf: &mut Config, _delta_time: f32) -> GameResult {
-        self.afterlock_cooldown = f32::max(0., self.afterlock_cooldown - _delta_time);
-        self.change_direction_cooldown = f32::max(0., self.change_direction_cooldown - _delta_time);
-        self.shoot_timeout = f32::max(0., self.shoot_timeout - _delta_time);
-        self.animation_cooldown = f32::max(0., self.animation_cooldown - _delta_time);
+    fn act(&mut self, act: &ActContext, _shots: &mut Vec<Shot>) -> GameResult {
+        let ActContext { sw, sh, obstacles, player, .. } = *act;
 
-        self.velocity_lerp(_delta_time, self.speed, 10., 10.);
-        self.props.pos.0 += self.props.velocity;
+        if self.brood.as_ref().is_some_and(|b| b.is_orphaned()) { self.health = 0.; }
+        if let Some(pool) = self.segment.as_ref().and_then(|s| s.pool.as_ref()) { self.health = pool.get(); }
 
-        if self.animation_cooldown == 0. { self.state = ActorState::Base; }
-        if self.health <= 0. {
-            conf.assets.audio.get_mut("boss_death_sound").unwrap().play(ctx)?; 
-            self.state = ActorState::Dead;
-        }
+        if self.afterlock_cooldown != 0. { return Ok(()); }
 
-        Ok(())
-    }
+        if let Some(brain) = &mut self.brain { brain.think(self.props.pos.0, player.get_pos(), obstacles, sw, sh); }
 
-    fn draw(&self, ctx: &mut Context, conf: &mut Config) -> GameResult {
-        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let radius = self.get_bcircle(sw, sh).1;
 
-        let sprite = match self.state {
-            ActorState::Shoot => { 
-                match self.props.forward.x as i32 {
-                    1 => conf.assets.sprites.get("boss_weird_ball_shoot_cardinals").unwrap(),
-                    _ => conf.assets.sprites.get("boss_weird_ball_shoot_diagonals").unwrap(),
-                }
-            },
-            _ => conf.assets.sprites.get("boss_weird_ball_base").unwrap(),
+        let following = self.segment.as_ref().is_some_and(|s| !s.is_head());
+        let dodge = match (&mut self.evasion, self.movement) {
+            (_, Movement::Still) | (None, _) => None,
+            (Some(evasion), _) => evasion.dodge(self.props.pos.0, radius, _shots, sw, sh),
         };
 
-        let draw_params = DrawParam::default()
-            .dest(self.props.pos)
-            .scale(self.scale_to_screen(sw, sh, sprite.dimensions()))
-            .offset([0.5, 0.5]);
-
-        match self.state {
-            ActorState::Damaged => graphics::draw(ctx, sprite, draw_params.color(Color::RED))?,
-            _ => graphics::draw(ctx, sprite, draw_params)?,
+        match (dodge, self.movement) {
+            _ if following => self.props.translation = Vec2::ZERO,
+            (Some(dir), _) => self.props.translation = dir,
+            (None, Movement::Still) => (),
+            (None, Movement::Chase) => self.chase_move(act),
+            (None, Movement::Wander) => self.wander_move(act),
+            (None, Movement::Flock(config)) => self.flock_move(act, &config),
+            (None, Movement::Charge(_)) => self.charge_move(act),
+            (None, Movement::Burrow(burrow)) if burrow.is_underground() => self.chase(act.sw, act.sh, act.obstacles, act.flow, act.player),
+            (None, Movement::Burrow(_)) => self.props.translation = Vec2::ZERO,
         }
 
-        self.draw_health_bar(ctx, sw, sh)?;
+        if self.is_attacking() { self.shoot(sw, sh, obstacles, _shots, player); }
 
-        if conf.draw_bcircle_model { self.draw_bcircle(ctx, (sw, sh))?; }
+        if let Some(spawner) = &mut self.spawner { spawner.request(); }
 
         Ok(())
     }
 
-    fn get_pos(&self) -> Vec2 { self.props.pos.into() }
-
-    fn get_scale(&self) -> Vec2 { self.props.scale }
+    /// Minions the spawner asked for, placed around it. A spawner that died this frame hatches nothing.
+    ///
+    fn take_spawns(&mut self, sw: f32, sh: f32) -> Vec<Box<dyn Actor>> {
+        if self.state == ActorState::Dead { return Vec::new(); }
 
-    fn get_velocity(&self) -> Vec2 { self.props.velocity }
+        let (pos, radius) = (self.get_pos(), self.get_bcircle(sw, sh).1);
+        let Spawner { template, brood, pending, .. } = match &mut self.spawner {
+            Some(spawner) => spawner,
+            None => return Vec::new(),
+        };
+        let (template, count) = match template {
+            Some(template) => (template, std::mem::take(pending)),
+            None => return Vec::new(),
+        };
 
-    fn get_translation(&self) -> Vec2 { self.props.translation }
+        (0..count)
+            .map(|_| {
+                let dir = Mat2::from_angle(thread_rng().gen_range(0. ..TAU)) * Vec2::X;
+                brood.join();
+                Box::new(Enemy {
+                    props: ActorProps {
+                        pos: (pos + dir * radius).into(),
+                        forward: dir,
+                        ..template.props
+                    },
+                    brood: Some(brood.clone()),
+                    ..(**template).clone()
+                }) as Box<dyn Actor>
+            })
+            .collect()
+    }
 
-    fn get_forward(&self) -> Vec2 { self.props.forward }
+    fn leave_creep(&mut self) -> Option<Creep> {
+        let puddle = self.death == OnDeath::Puddle && self.state == ActorState::Dead;
+        let trail = self.leaves_creep && self.creep_cooldown == 0. && self.props.velocity != Vec2::ZERO;
+        if !puddle && !trail {
+            return None;
+        }
 
-    fn set_pos(&mut self, new_pos: Vec2) { self.props.pos = new_pos.into(); }
+        self.creep_cooldown = CREEP_COOLDOWN;
 
-    fn set_scale(&mut self, new_scale: Vec2) { self.props.scale = new_scale; }
+        Some(Creep::new(self.get_pos(), CREEP_SLIME_DAMAGE, CREEP_SLIME_SLOW, CreepTag::Enemy))
+    }
 
-    fn set_velocity(&mut self, new_velocity: Vec2) { self.props.velocity = new_velocity; } 
+    fn is_hittable(&self) -> bool {
+        match self.movement {
+            Movement::Burrow(burrow) => burrow.is_surfaced(),
+            _ => true,
+        }
+    }
 
-    fn set_translation(&mut self, new_translation: Vec2) { self.props.translation = new_translation; }
+    fn blocks_clear(&self) -> bool { self.blocks_clear }
 
-    fn set_forward(&mut self, new_forward: Vec2) { self.props.forward = new_forward; } 
+    fn is_anchored(&self) -> bool { self.anchored }
 
-    fn get_health(&self) -> f32 { self.health }
-
-    fn get_state(&self) -> ActorState { self.state }
+    fn shut_off(&mut self) {
+        self.attack = Attack::None;
+        self.damage = 0.;
+        self.spawner = None;
+    }
 
-    fn damage(&mut self, dmg: f32) { 
-        self.health -= dmg; 
-        self.state = ActorState::Damaged;
-        self.animation_cooldown = ANIMATION_COOLDOWN;
+    fn get_shields(&self, sw: f32, sh: f32) -> Vec<((Vec2Wrap, f32), Vec2)> {
+        let (pos, radius) = self.get_bcircle(sw, sh);
+        match &self.orbit {
+            Some(orbit) => orbit.offsets(radius).into_iter()
+                .map(|o| (((pos.0 + o).into(), radius * ORBIT_BLOCKER_SIZE), o.perp() * orbit.config.speed))
+                .collect(),
+            None => Vec::new(),
+        }
     }
 
-    fn get_damage(&self) -> f32 { return self.damage }
+    fn damage_shield(&mut self, n: usize, dmg: f32) {
+        if let Some(orbit) = &mut self.orbit { orbit.damage(n, dmg); }
+    }
 
-    fn get_tag(&self) -> ActorTag { ActorTag::Enemy }
+    fn draw_layer(&self) -> i32 { self.segment.as_ref().map_or(0, |s| -(s.index as i32)) }
 
-    fn act(&mut self, sw: f32, sh: f32, _grid: &[[i32; ROOM_WIDTH]], _obstacles: &Vec<Box<dyn Stationary>>, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult {
-        if self.afterlock_cooldown == 0. {
-            self.wander(sw, sh, _grid);
-            self.shoot(sw, sh, _obstacles, _shots, _player);
+    fn on_death(&mut self, grid: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], sw: f32, sh: f32) -> Vec<Box<dyn Actor>> {
+        if let Some(spawner) = &self.spawner {
+            if spawner.config.minions_die { spawner.brood.orphan(); }
         }
-        Ok(())
+        if let Some(brood) = self.brood.take() { brood.leave(); }
+
+        let config = match self.death {
+            OnDeath::Split(config) => config,
+            _ => return Vec::new(),
+        };
+        let scale = self.props.scale * config.scale;
+        if scale.max_element() < config.min_scale { return Vec::new(); }
+
+        let spread = self.get_bcircle(sw, sh).1 * 0.5;
+        let speed = self.speed * config.speed;
+        let health = self.max_health * config.health;
+
+        (0..config.count)
+            .map(|k| {
+                let dir = Mat2::from_angle(TAU * k as f32 / config.count as f32) * Vec2::X;
+                Box::new(Enemy {
+                    props: ActorProps {
+                        pos: snap_to_free_tile(self.get_pos() + dir * spread, grid, sw, sh).into(),
+                        scale,
+                        translation: dir,
+                        forward: dir,
+                        velocity: dir * speed,
+                        ..self.props
+                    },
+                    state: ActorState::Base,
+                    health,
+                    max_health: health,
+                    speed,
+                    animation_cooldown: 0.,
+                    hazard_damage: 0.,
+                    ..self.clone()
+                }) as Box<dyn Actor>
+            })
+            .collect()
     }
 
     fn as_any(&self) -> &dyn Any { self }
@@ -559,67 +1190,43 @@ impl Actor for BossWeirdBall {
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
 }
 
-impl Wanderer for BossWeirdBall {
-    fn get_change_direction_cooldown(&self) -> f32 { self.change_direction_cooldown }
-
-    fn set_change_direction_cooldown(&mut self, cd: f32) { self.change_direction_cooldown = cd; }
-}
+impl Chaser for Enemy {
+    fn chase(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, flow: &FlowField, player: &Player) {
+        if self.afterlock_cooldown == 0. {
+            let (mut cp, mut cn) = (Vec2::ZERO, Vec2::ZERO);
+            let mut ct = 0.;
+            let mut target = player.get_pos();
 
-impl BossWeirdBall {
-    fn draw_health_bar(&self, ctx: &mut Context, sw: f32, sh: f32) -> GameResult {
-        let bbox = self.get_bbox(sw, sh);
-        let (hbw, hbh) = (bbox.w * 1.4, sh * 0.01);
-        let (hbx, hby) = (bbox.x - (hbw - bbox.w) * 0.5, bbox.y - hbh * 2.);
+            if obstacles.iter()
+                .filter(|o| { ray_vs_rect(&self.get_pos(), &(player.get_pos() - self.get_pos()), &o.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct) && ct < 1. })
+                .count() != 0 {
+                target = self.find_path(flow, sw, sh);
+            }
 
-        let bar = MeshBuilder::new()
-            .rectangle(DrawMode::fill(), Rect::new(hbx, hby, hbw / self.max_health * self.health, hbh), Color::RED)?
-            .rectangle(DrawMode::stroke(3.), Rect::new(hbx, hby, hbw, hbh), Color::BLACK)?
-            .build(ctx)?;
+            self.props.translation = (target - self.get_pos()).normalize_or_zero();
+            self.props.forward = self.props.translation;
+        }
+    }
+}
 
-        graphics::draw(ctx, &bar, DrawParam::default())?;
+impl Wanderer for Enemy {
+    fn get_change_direction_cooldown(&self) -> f32 { self.change_direction_cooldown }
 
-        Ok(())
-    }
+    fn set_change_direction_cooldown(&mut self, cd: f32) { self.change_direction_cooldown = cd; }
 }
 
-impl Shooter for BossWeirdBall {
-    fn shoot(&mut self, _sw: f32, _sh: f32, _obstacles: &Vec<Box<dyn Stationary>>, shots: &mut Vec<Shot>, _player: &Player) {
-        if self.shoot_timeout != 0. 
+impl Shooter for Enemy {
+    fn shoot(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, shots: &mut Vec<Shot>, player: &Player) {
+        if self.shoot_timeout != 0.
             || self.afterlock_cooldown != 0. {
             return;
         }
 
-        self.props.forward = match thread_rng().gen_bool(0.5) {
-            true => Vec2::X,
-            false => Vec2::Y,
-        };
-        self.state = ActorState::Shoot;
-        self.shoot_timeout = 1. / self.shoot_rate;
-        self.animation_cooldown = ANIMATION_COOLDOWN / self.shoot_rate;
-
-        let mut shot_dir = match self.props.forward.x as i32 {
-            1 => Vec2::X,
-            _ => Vec2::ONE,
-        }.normalize();
-
-        for _ in 0..4 {
-            let shot = Shot {
-                props: ActorProps {
-                    pos: self.props.pos,
-                    scale: Vec2::splat(SHOT_SCALE),
-                    translation: shot_dir,
-                    forward: shot_dir,
-                    velocity: Vec2::ZERO,
-                },
-                spawn_pos: self.props.pos,
-                speed: SHOT_SPEED,
-                range: self.shoot_range,
-                damage: self.damage,
-                tag: ShotTag::Enemy,
-            };
-
-            shots.push(shot);
-            shot_dir = shot_dir.perp();
+        match self.attack {
+            Attack::None => (),
+            Attack::Aimed => self.shoot_aimed(sw, sh, obstacles, shots, player),
+            Attack::Cross => self.shoot_cross(shots),
+            Attack::Volley(config) => self.shoot_volley(sw, sh, shots, &config),
         }
     }
 
@@ -0,0 +1,111 @@
+use crate::{
+    consts::*,
+    utils::*,
+    traits::*,
+};
+use glam::f32::Vec2;
+
+/// Snapshot of an enemy taken before the enemies act, so they can look at each other.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ally {
+    pub pos: Vec2,
+    pub velocity: Vec2,
+    pub flocks: bool,
+}
+
+/// Enemies of a room bucketed by tile, for cheap neighbourhood queries.
+///
+#[derive(Debug, Clone, Default)]
+pub struct AllyGrid {
+    allies: Vec<Ally>,
+    cells: Vec<Vec<usize>>,
+    tile: Vec2,
+}
+
+impl AllyGrid {
+    pub fn new(enemies: &[Box<dyn Actor>], sw: f32, sh: f32) -> Self {
+        let allies = enemies.iter()
+            .map(|e| Ally { pos: e.get_pos(), velocity: e.get_velocity(), flocks: e.flocks() })
+            .collect::<Vec<_>>();
+
+        AllyGrid::from_allies(allies, sw, sh)
+    }
+
+    pub fn from_allies(allies: Vec<Ally>, sw: f32, sh: f32) -> Self {
+        let mut cells = vec![Vec::new(); ROOM_WIDTH * ROOM_HEIGHT];
+        for (idx, ally) in allies.iter().enumerate() {
+            let (i, j) = pos_to_room_coords(ally.pos, sw, sh);
+            cells[i.min(ROOM_HEIGHT - 1) * ROOM_WIDTH + j.min(ROOM_WIDTH - 1)].push(idx);
+        }
+
+        Self {
+            allies,
+            cells,
+            tile: Vec2::new(sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32),
+        }
+    }
+
+    pub fn len(&self) -> usize { self.allies.len() }
+
+    pub fn is_empty(&self) -> bool { self.allies.is_empty() }
+
+    /// Allies within `radius` of `pos`, the ally standing exactly at `pos` is the one asking and is skipped.
+    ///
+    pub fn nearby(&self, pos: Vec2, radius: f32) -> Vec<Ally> {
+        if self.cells.is_empty() { return Vec::new(); }
+
+        let (min, max) = ((pos - radius) / self.tile, (pos + radius) / self.tile);
+        let (i0, j0) = (min.y.max(0.) as usize, min.x.max(0.) as usize);
+        let (i1, j1) = ((max.y.max(0.) as usize).min(ROOM_HEIGHT - 1), (max.x.max(0.) as usize).min(ROOM_WIDTH - 1));
+
+        let mut nearby = Vec::new();
+        for i in i0..=i1 {
+            for j in j0..=j1 {
+                nearby.extend(self.cells[i * ROOM_WIDTH + j].iter()
+                    .map(|&idx| self.allies[idx])
+                    .filter(|a| a.pos != pos && a.pos.distance(pos) <= radius));
+            }
+        }
+
+        nearby
+    }
+}
+
+/// Weights of the flocking rules, all of them are applied within `radius`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlockConfig {
+    pub radius: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    pub attraction: f32,
+}
+
+/// Steering of a swarm member: keep apart from every ally, move along and towards the rest of the swarm,
+/// and head in the `attraction` direction.
+///
+pub fn flock(pos: Vec2, allies: &[Ally], attraction: Vec2, config: &FlockConfig) -> Vec2 {
+    let mut separation = Vec2::ZERO;
+    for ally in allies {
+        let away = pos - ally.pos;
+        separation += away.normalize_or_zero() * (1. - away.length() / config.radius).max(0.);
+    }
+
+    let swarm = allies.iter().filter(|a| a.flocks).collect::<Vec<_>>();
+    let (alignment, cohesion) = match swarm.is_empty() {
+        true => (Vec2::ZERO, Vec2::ZERO),
+        false => {
+            let n = swarm.len() as f32;
+            let velocity = swarm.iter().map(|a| &a.velocity).sum::<Vec2>() / n;
+            let center = swarm.iter().map(|a| &a.pos).sum::<Vec2>() / n;
+            (velocity.normalize_or_zero(), (center - pos).normalize_or_zero())
+        },
+    };
+
+    (separation * config.separation
+        + alignment * config.alignment
+        + cohesion * config.cohesion
+        + attraction.normalize_or_zero() * config.attraction).clamp_length_max(1.)
+}
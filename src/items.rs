@@ -70,6 +70,10 @@ impl Actor for Collectable {
         Ok(())
     }
 
+    fn get_props(&self) -> &ActorProps { &self.props }
+
+    fn get_props_mut(&mut self) -> &mut ActorProps { &mut self.props }
+
     fn get_pos(&self) -> Vec2 { self.props.pos.into() }
 
     fn get_scale(&self) -> Vec2 { self.props.scale }
@@ -136,6 +140,9 @@ impl Collectable {
 #[derive(Debug, Copy, Clone)]
 pub enum ItemPassive {
     IncreaseMaxHealth(f32),
+    PukeTrail,
+    Compass,
+    Map,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -160,6 +167,9 @@ impl Item {
         match self.tag {
             ItemTag::Passive(p) => match p {
                 ItemPassive::IncreaseMaxHealth(x) => player.max_health += x,
+                ItemPassive::PukeTrail => player.puke_trail = true,
+                ItemPassive::Compass => player.has_compass = true,
+                ItemPassive::Map => player.has_map = true,
             },
             _ => (),
         }
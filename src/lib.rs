@@ -8,4 +8,12 @@ pub mod ui_elements;
 pub mod items;
 pub mod player;
 pub mod enemies;
+pub mod bestiary;
 pub mod shots;
+pub mod puzzles;
+pub mod creep;
+pub mod difficulty;
+pub mod pathfinding;
+pub mod ai;
+pub mod aiming;
+pub mod flocking;
@@ -16,6 +16,7 @@ use std::{
 };
 use puker::{
     assets::*,
+    bestiary::*,
     consts::*,
     scenes::*,
     traits::*,
@@ -29,8 +30,11 @@ struct MainState {
 
 impl MainState {
     fn new(ctx: &mut Context, conf: &Conf) -> GameResult<MainState> {
+        let assets = Assets::new(ctx)?;
+        let bestiary = Bestiary::new(ctx, &assets)?;
         let config = Rc::new(RefCell::new(Config {
-            assets: Assets::new(ctx)?,
+            assets,
+            bestiary,
             screen_width: conf.window_mode.width,
             screen_height: conf.window_mode.height,
             window_width: conf.window_mode.width,
@@ -42,6 +46,8 @@ impl MainState {
             current_state: State::MainMenu,
             previous_state: State::MainMenu,
             level: 0,
+            endless: false,
+            best_depth: load_best_depth(ctx),
         }));
         let mut scenes = HashMap::<State, Box<dyn Scene>>::new();
         scenes.insert(State::PauseMenu, Box::new(PauseMenuScene::new(&config)));
@@ -69,6 +75,11 @@ impl EventHandler for MainState {
 
             let scene = self.config.borrow().current_state;
 
+            // A new run builds its dungeon here, where generation errors can be returned.
+            if scene == State::Transition && !self.scenes.contains_key(&State::Play) {
+                self.scenes.insert(State::Play, Box::new(PlayScene::new(&self.config)?));
+            }
+
             match scene {
                 State::Play => input::mouse::set_cursor_grabbed(ctx, true)?,
                 _ => input::mouse::set_cursor_grabbed(ctx, false)?,
@@ -156,9 +167,14 @@ impl EventHandler for MainState {
             State::New => {
                 self.config.borrow_mut().current_state = State::Transition;
                 self.config.borrow_mut().level = 1;
-                self.scenes.insert(State::Play, Box::new(PlayScene::new(&self.config)));
+                self.config.borrow_mut().endless = false;
+                self.scenes.remove(&State::Play);
                 self.scenes.insert(State::Transition, Box::new(LevelTransitionScene::new(&self.config)));
             }
+            State::Endless => {
+                self.config.borrow_mut().current_state = State::Transition;
+                self.config.borrow_mut().endless = true;
+            },
             State::Quit => ggez::event::quit(_ctx),
             State::MainMenu => { self.scenes.remove(&State::Play); },
             _ => (),
@@ -190,6 +206,15 @@ impl EventHandler for MainState {
             .mouse_motion_event(_ctx, _x, _y, _dx, _dy);
     }
 
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {
+        let scene = self.config.borrow().current_state;
+
+        self.scenes
+            .get_mut(&scene)
+            .unwrap()
+            .mouse_wheel_event(_ctx, _x, _y);
+    }
+
     fn resize_event(&mut self, _ctx: &mut Context, _width: f32, _height: f32) {
         let mut conf = self.config.borrow_mut();
         conf.window_width = _width;
@@ -0,0 +1,235 @@
+use crate::{
+    consts::*,
+    utils::*,
+    dungeon::BlockTag,
+};
+use std::{
+    collections::BinaryHeap,
+    cmp::Reverse,
+};
+use glam::f32::Vec2;
+
+/// How an actor moves through a room, every layer has its own flow field and collision rules.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MovementLayer {
+    Ground,
+    Flying,
+    Phasing,
+}
+
+impl MovementLayer {
+    /// Flyers pass over everything standing on the floor, phasing actors pass through anything.
+    ///
+    pub fn is_blocked_by(&self, tag: &BlockTag) -> bool {
+        match self {
+            MovementLayer::Ground => !tag.is_floor() && !matches!(tag, BlockTag::Hatch(_)),
+            MovementLayer::Flying => matches!(tag, BlockTag::Wall | BlockTag::Door { .. } | BlockTag::Gate(false)),
+            MovementLayer::Phasing => false,
+        }
+    }
+
+    /// Only grounded actors are hurt and slowed by spikes, fire, creep and surfaces.
+    ///
+    pub fn is_grounded(&self) -> bool { *self == MovementLayer::Ground }
+}
+
+/// Clearance in tiles an actor with the given bounding circle radius needs to pass between obstacles.
+/// A clearance of 1 means any free tile will do.
+///
+pub fn required_clearance(radius: f32, sw: f32, sh: f32) -> u32 {
+    let tile = f32::min(sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32);
+    u32::max((radius / tile + 0.5).ceil() as u32, 1)
+}
+
+/// Free space around every tile, that is the distance to the closest obstacle or room edge in tiles.
+/// Obstacle tiles have a clearance of 0.
+///
+pub fn clearance_grid(obstacles: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT]) -> [[u32; ROOM_WIDTH]; ROOM_HEIGHT] {
+    let mut clearance = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
+
+    for i in 0..ROOM_HEIGHT {
+        for j in 0..ROOM_WIDTH {
+            if obstacles[i][j] != 0 { continue; }
+
+            let (ci, cj) = (i as i32, j as i32);
+            let mut k = 1;
+            'grow: loop {
+                for ni in ci - k..=ci + k {
+                    for nj in cj - k..=cj + k {
+                        if !(0..ROOM_HEIGHT as i32).contains(&ni) || !(0..ROOM_WIDTH as i32).contains(&nj) || obstacles[ni as usize][nj as usize] != 0 {
+                            break 'grow;
+                        }
+                    }
+                }
+                k += 1;
+            }
+            clearance[i][j] = k as u32;
+        }
+    }
+
+    clearance
+}
+
+const NEIGHBOURS: [(i32, i32); 8] = [(-1, 0), (0, -1), (0, 1), (1, 0), (-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+/// Distance field towards a single tile of the room, with the best step direction for every tile.
+/// Tiles with less free space than the clearance are treated as obstacles, so large actors don't get stuck.
+/// It is cached by the room and only rebuilt when the target tile or the obstacles change.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct FlowField {
+    target: Option<(usize, usize)>,
+    clearance: u32,
+    obstacles: [[i32; ROOM_WIDTH]; ROOM_HEIGHT],
+    grid: [[i32; ROOM_WIDTH]; ROOM_HEIGHT],
+    directions: [[Vec2; ROOM_WIDTH]; ROOM_HEIGHT],
+}
+
+impl Default for FlowField {
+    fn default() -> Self {
+        Self {
+            target: None,
+            clearance: 1,
+            obstacles: [[0; ROOM_WIDTH]; ROOM_HEIGHT],
+            grid: [[0; ROOM_WIDTH]; ROOM_HEIGHT],
+            directions: [[Vec2::ZERO; ROOM_WIDTH]; ROOM_HEIGHT],
+        }
+    }
+}
+
+impl FlowField {
+    pub fn new(obstacles: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], target: Vec2, clearance: u32, sw: f32, sh: f32) -> Self {
+        let mut flow = FlowField { clearance, ..Default::default() };
+        flow.rebuild(obstacles, FlowField::target_tile(target, sw, sh));
+        flow
+    }
+
+    /// Rebuilds the field if the target moved to another tile or the obstacles changed.
+    /// Returns whether a rebuild happened.
+    ///
+    pub fn update(&mut self, obstacles: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], target: Vec2, sw: f32, sh: f32) -> bool {
+        let target = FlowField::target_tile(target, sw, sh);
+
+        if target == self.target && *obstacles == self.obstacles { return false; }
+
+        self.rebuild(obstacles, target);
+        true
+    }
+
+    /// Distance grid in the format of the room grid: obstacles are `i32::MIN`, unreachable tiles are 0
+    /// and reachable tiles grow towards the target, which holds `i32::MAX`.
+    ///
+    pub fn get_grid(&self) -> &[[i32; ROOM_WIDTH]; ROOM_HEIGHT] { &self.grid }
+
+    pub fn get_clearance(&self) -> u32 { self.clearance }
+
+    pub fn get_direction(&self, (i, j): (usize, usize)) -> Vec2 {
+        if i >= ROOM_HEIGHT || j >= ROOM_WIDTH { return Vec2::ZERO; }
+        self.directions[i][j]
+    }
+
+    /// Steering direction at an arbitrary position, blended from the directions of the surrounding tiles.
+    ///
+    pub fn steer(&self, pos: Vec2, sw: f32, sh: f32) -> Vec2 {
+        let (i, j) = pos_to_room_coords(pos, sw, sh);
+        let own = self.get_direction((i, j));
+        if own == Vec2::ZERO { return own; }
+
+        let (bw, bh) = (sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32);
+        let (fx, fy) = (pos.x / bw - 0.5, pos.y / bh - 0.5);
+        let (j0, i0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - j0, fy - i0);
+
+        let mut dir = Vec2::ZERO;
+        for (di, dj, w) in [(0., 0., (1. - tx) * (1. - ty)), (0., 1., tx * (1. - ty)), (1., 0., (1. - tx) * ty), (1., 1., tx * ty)] {
+            let (ci, cj) = (i0 + di, j0 + dj);
+            if ci < 0. || cj < 0. { continue; }
+
+            dir += self.get_direction((ci as usize, cj as usize)) * w;
+        }
+
+        if dir.dot(own) <= 0. { own } else { dir.normalize() }
+    }
+
+    fn target_tile(target: Vec2, sw: f32, sh: f32) -> Option<(usize, usize)> {
+        if target.x < 0. || target.y < 0. { return None; }
+
+        let (ti, tj) = pos_to_room_coords(target, sw, sh);
+        if ti >= ROOM_HEIGHT || tj >= ROOM_WIDTH { return None; }
+
+        Some((ti, tj))
+    }
+
+    fn is_free(&self, i: i32, j: i32) -> bool {
+        (0..ROOM_HEIGHT as i32).contains(&i) && (0..ROOM_WIDTH as i32).contains(&j) && self.grid[i as usize][j as usize] != i32::MIN
+    }
+
+    /// Dijkstra from the target over 8 neighbours.
+    /// Diagonal steps are only taken when both tiles beside them are free, so paths never cut corners.
+    ///
+    fn rebuild(&mut self, obstacles: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], target: Option<(usize, usize)>) {
+        self.target = target;
+        self.obstacles = *obstacles;
+        self.grid = *obstacles;
+        self.directions = [[Vec2::ZERO; ROOM_WIDTH]; ROOM_HEIGHT];
+
+        let clearance = clearance_grid(obstacles);
+        for (row, clearance) in self.grid.iter_mut().zip(clearance.iter()) {
+            for (tile, c) in row.iter_mut().zip(clearance.iter()) {
+                if *c < self.clearance { *tile = i32::MIN; }
+            }
+        }
+
+        let (ti, tj) = match target {
+            Some(t) if self.grid[t.0][t.1] == 0 => t,
+            _ => return,
+        };
+
+        let mut costs = [[u32::MAX; ROOM_WIDTH]; ROOM_HEIGHT];
+        let mut q = BinaryHeap::new();
+        costs[ti][tj] = 0;
+        q.push(Reverse((0, ti, tj)));
+
+        while let Some(Reverse((cost, i, j))) = q.pop() {
+            if cost > costs[i][j] { continue; }
+
+            for (di, dj) in NEIGHBOURS {
+                let (ni, nj) = (i as i32 + di, j as i32 + dj);
+                if !self.is_free(ni, nj) { continue; }
+
+                let is_diagonal = di != 0 && dj != 0;
+                if is_diagonal && (!self.is_free(i as i32 + di, j as i32) || !self.is_free(i as i32, j as i32 + dj)) { continue; }
+
+                let next = cost + if is_diagonal { PATH_DIAGONAL_COST } else { PATH_STRAIGHT_COST };
+                let (ni, nj) = (ni as usize, nj as usize);
+                if next < costs[ni][nj] {
+                    costs[ni][nj] = next;
+                    q.push(Reverse((next, ni, nj)));
+                }
+            }
+        }
+
+        for i in 0..ROOM_HEIGHT {
+            for j in 0..ROOM_WIDTH {
+                if costs[i][j] == u32::MAX { continue; }
+
+                self.grid[i][j] = i32::MAX - costs[i][j] as i32;
+                if (i, j) == (ti, tj) { continue; }
+
+                let best = NEIGHBOURS.iter()
+                    .map(|&(di, dj)| (di, dj, i as i32 + di, j as i32 + dj))
+                    .filter(|&(di, dj, ni, nj)| {
+                        self.is_free(ni, nj) && (di == 0 || dj == 0 || (self.is_free(i as i32 + di, j as i32) && self.is_free(i as i32, j as i32 + dj)))
+                    })
+                    .min_by_key(|&(di, dj, ni, nj)| {
+                        costs[ni as usize][nj as usize].saturating_add(if di != 0 && dj != 0 { PATH_DIAGONAL_COST } else { PATH_STRAIGHT_COST })
+                    });
+
+                if let Some((di, dj, _, _)) = best {
+                    self.directions[i][j] = Vec2::new(dj as f32, di as f32).normalize();
+                }
+            }
+        }
+    }
+}
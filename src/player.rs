@@ -9,6 +9,7 @@ use crate::{
     consts::*,
     traits::*,
     shots::*,
+    creep::*,
     items::Item,
 };
 use glam::f32::{Vec2};
@@ -32,6 +33,10 @@ pub struct Player {
     pub afterlock_cooldown: f32,
     pub item: Option<Item>,
     pub item_pick_cooldown: f32,
+    pub puke_trail: bool,
+    pub creep_cooldown: f32,
+    pub has_compass: bool,
+    pub has_map: bool,
 }
 
 impl Default for Player {
@@ -43,6 +48,8 @@ impl Default for Player {
                 translation: Vec2::ZERO,
                 forward: Vec2::ZERO,
                 velocity: Vec2::ZERO,
+                surface: Surface::Normal,
+                slow: 1.,
             },
             speed: PLAYER_SPEED,
             health: PLAYER_HEALTH,
@@ -57,6 +64,10 @@ impl Default for Player {
             afterlock_cooldown: PLAYER_AFTERLOCK_COOLDOWN,
             item: None,
             item_pick_cooldown: 0.,
+            puke_trail: false,
+            creep_cooldown: 0.,
+            has_compass: false,
+            has_map: false,
         }
     }
 }
@@ -75,6 +86,7 @@ impl Actor for Player {
         self.shoot_timeout = f32::max(0., self.shoot_timeout - _delta_time);
         self.damaged_cooldown = f32::max(0., self.damaged_cooldown - _delta_time);
         self.animation_cooldown = f32::max(0., self.animation_cooldown - _delta_time);
+        self.creep_cooldown = f32::max(0., self.creep_cooldown - _delta_time);
 
         if self.animation_cooldown == 0. { self.state = ActorState::Base; }
         if self.health <= 0. {
@@ -113,6 +125,10 @@ impl Actor for Player {
         Ok(())
     }
 
+    fn get_props(&self) -> &ActorProps { &self.props }
+
+    fn get_props_mut(&mut self) -> &mut ActorProps { &mut self.props }
+
     fn get_pos(&self) -> Vec2 { self.props.pos.into() }
 
     fn get_scale(&self) -> Vec2 { self.props.scale }
@@ -148,6 +164,16 @@ impl Actor for Player {
 
     fn get_tag(&self) -> ActorTag { ActorTag::Player }
 
+    fn leave_creep(&mut self) -> Option<Creep> {
+        if !self.puke_trail || self.creep_cooldown != 0. || self.props.velocity == Vec2::ZERO {
+            return None;
+        }
+
+        self.creep_cooldown = CREEP_COOLDOWN;
+
+        Some(Creep::new(self.get_pos(), CREEP_PUKE_DAMAGE, CREEP_PUKE_SLOW, CreepTag::Player))
+    }
+
     fn as_any(&self) -> &dyn Any { self }
 
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
@@ -174,6 +200,8 @@ impl Player {
                 translation: shot_dir,
                 forward: shot_dir,
                 velocity: Vec2::ZERO,
+                surface: Surface::Normal,
+                slow: 1.,
             },
             spawn_pos: self.props.pos,
             speed: SHOT_SPEED,
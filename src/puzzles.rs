@@ -0,0 +1,151 @@
+use crate::{
+    utils::Errors,
+    consts::{ROOM_HEIGHT, ROOM_WIDTH},
+};
+
+/// Maximum number of separate channels a room layout can wire.
+/// Channels are referenced by the digits `0`-`9` in the layout.
+///
+pub const PUZZLE_CHANNELS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MechanismKind {
+    Plate,
+    Switch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PuzzleAction {
+    OpenDoors,
+    RetractSpikes,
+    SpawnReward,
+    ToggleGates,
+}
+
+impl PuzzleAction {
+    /// Latching actions stay active once triggered, even if the channel is released afterwards.
+    ///
+    pub fn latches(&self) -> bool {
+        matches!(self, PuzzleAction::OpenDoors | PuzzleAction::SpawnReward)
+    }
+
+    /// Spikes and gates are driven tile by tile, the rest acts on the whole room.
+    ///
+    pub fn has_targets(&self) -> bool {
+        matches!(self, PuzzleAction::RetractSpikes | PuzzleAction::ToggleGates)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wire {
+    pub channel: usize,
+    pub kind: MechanismKind,
+    pub action: PuzzleAction,
+    pub targets: Vec<(usize, usize)>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Puzzle {
+    pub wires: Vec<Wire>,
+}
+
+impl Puzzle {
+    /// Parses the wiring lines that follow the tiles of a room layout.
+    /// Every line has the form `<channel> <plate|switch> <doors|spikes|reward|gates> [row,col ...]`,
+    /// spikes and gates wires list the tiles they drive and the others list none.
+    ///
+    pub fn parse(lines: &[&str]) -> Result<Self, Errors> {
+        let mut wires = Vec::new();
+
+        for line in lines.iter().filter(|l| !l.is_empty()) {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            if tokens.len() < 3 { return Err(Errors::LayoutParse(line.to_string())); }
+
+            let channel = match tokens[0].parse::<usize>() {
+                Ok(c) if c < PUZZLE_CHANNELS => c,
+                _ => return Err(Errors::LayoutParse(line.to_string())),
+            };
+
+            let kind = match tokens[1] {
+                "plate" => MechanismKind::Plate,
+                "switch" => MechanismKind::Switch,
+                _ => return Err(Errors::LayoutParse(line.to_string())),
+            };
+
+            let action = match tokens[2] {
+                "doors" => PuzzleAction::OpenDoors,
+                "spikes" => PuzzleAction::RetractSpikes,
+                "reward" => PuzzleAction::SpawnReward,
+                "gates" => PuzzleAction::ToggleGates,
+                _ => return Err(Errors::LayoutParse(line.to_string())),
+            };
+
+            let targets = tokens[3..].iter()
+                .map(|t| match t.split_once(',').map(|(i, j)| (i.parse::<usize>(), j.parse::<usize>())) {
+                    Some((Ok(i), Ok(j))) if i < ROOM_HEIGHT && j < ROOM_WIDTH => Ok((i, j)),
+                    _ => Err(Errors::LayoutParse(line.to_string())),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if targets.is_empty() == action.has_targets() {
+                return Err(Errors::LayoutParse(line.to_string()));
+            }
+
+            if wires.iter().any(|w: &Wire| w.channel == channel && w.kind != kind) {
+                return Err(Errors::LayoutParse(line.to_string()));
+            }
+
+            wires.push(Wire {
+                channel,
+                kind,
+                action,
+                targets,
+                is_active: false,
+            });
+        }
+
+        Ok(Self { wires })
+    }
+
+    pub fn kind_of(&self, channel: usize) -> MechanismKind {
+        match self.wires.iter().find(|w| w.channel == channel) {
+            Some(w) => w.kind,
+            None => MechanismKind::Plate,
+        }
+    }
+
+    /// The puzzle is solved when every wire that holds the doors is active.
+    /// Rooms without such wires are always solved.
+    ///
+    pub fn is_solved(&self) -> bool {
+        self.wires.iter()
+            .filter(|w| w.action == PuzzleAction::OpenDoors)
+            .all(|w| w.is_active)
+    }
+
+    /// Whether the wires with `action` that drive the tile are active, `None` when no such wire drives it.
+    ///
+    pub fn drives(&self, action: PuzzleAction, coords: (usize, usize)) -> Option<bool> {
+        self.wires.iter()
+            .filter(|w| w.action == action && w.targets.contains(&coords))
+            .map(|w| w.is_active)
+            .reduce(|a, b| a || b)
+    }
+
+    /// Updates the wires from the current channel states and returns the actions whose state changed.
+    ///
+    pub fn update(&mut self, channels: &[bool; PUZZLE_CHANNELS]) -> Vec<(PuzzleAction, bool)> {
+        let mut changed = Vec::new();
+
+        for w in self.wires.iter_mut() {
+            let active = channels[w.channel] || (w.is_active && w.action.latches());
+
+            if active != w.is_active {
+                w.is_active = active;
+                changed.push((w.action, active));
+            }
+        }
+
+        changed
+    }
+}
@@ -1,5 +1,5 @@
 use ggez::{
-    graphics::{self, DrawMode, Color, DrawParam, Rect, Mesh},
+    graphics::{self, DrawMode, Color, DrawParam, Rect, Mesh, MeshBuilder},
     Context,
     GameResult,
     mint::{Point2},
@@ -24,6 +24,7 @@ use crate::{
     traits::*,
     ui_elements::*,
     items::*,
+    pathfinding::MovementLayer,
 };
 
 pub struct PlayScene {
@@ -32,25 +33,27 @@ pub struct PlayScene {
     dungeon: Dungeon,
     cur_room: (usize, usize),
     overlay: Overlay,
+    map: FullMap,
 }
 
 impl PlayScene {
-    pub fn new(config: &Rc<RefCell<Config>>) -> Self {
+    pub fn new(config: &Rc<RefCell<Config>>) -> GameResult<Self> {
         let (sw, sh) = (config.borrow().screen_width, config.borrow().screen_height);
 
         let config = Rc::clone(config);
         let player = Player::default();
-        let dungeon = Dungeon::generate_dungeon((sw, sh), config.borrow().level);
+        let dungeon = Dungeon::generate_dungeon((sw, sh), config.borrow().level, &config.borrow().bestiary)?;
         let cur_room = Dungeon::get_start_room_coords();
         let overlay = Overlay::new(&player, &dungeon, cur_room);
 
-        Self {
+        Ok(Self {
             config,
             player,
             dungeon,
             cur_room,
             overlay,
-        }
+            map: FullMap::default(),
+        })
     }
 
     fn handle_input(&mut self, ctx: &mut Context) -> GameResult {
@@ -109,8 +112,9 @@ impl PlayScene {
         let mut ct = 0.;
         let room = self.dungeon.get_room_mut(self.cur_room)?.unwrap();
         let mut next_level = false;
+        let mut pushed_rock = None;
 
-        for o in room.obstacles.iter_mut() {
+        for (idx, o) in room.obstacles.iter_mut().enumerate() {
             let obst = o.as_any_mut().downcast_mut::<Block>().unwrap();
 
             if dynamic_circle_vs_rect(&self.player.get_bcircle(sw, sh), &obst.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct, delta_time) {
@@ -132,8 +136,22 @@ impl PlayScene {
                         }
                         else { self.player.props.pos.0 -= cn.normalize() * ct; }
                     },
-                    BlockTag::Spikes => {
-                        if obst.get_bbox(sw, sh).contains(self.player.props.pos) { self.player.damage(1.); }
+                    BlockTag::Spikes { .. } => {
+                        if obst.tag.is_hazard() && obst.get_bbox(sw, sh).contains(self.player.props.pos) { self.player.damage(SPIKES_PLAYER_DAMAGE); }
+                    },
+                    BlockTag::Plate { .. } | BlockTag::Gate(true) => (),
+                    BlockTag::Fire { health, .. } => if health > 0. {
+                        self.player.damage(FIRE_DAMAGE);
+                        self.player.props.pos.0 -= cn.normalize() * ct;
+                    },
+                    BlockTag::Rock => {
+                        let dir = match cn.x.abs() > cn.y.abs() {
+                            true => Vec2::new(cn.x.signum(), 0.),
+                            false => Vec2::new(0., cn.y.signum()),
+                        };
+                        if self.player.props.translation.dot(dir) > 0.5 { pushed_rock = Some((idx, dir)); }
+
+                        self.player.props.pos.0 -= cn.normalize() * ct;
                     },
                     BlockTag::Hatch(is_open) => {
                         if is_open {
@@ -166,11 +184,20 @@ impl PlayScene {
             }
 
             match obst.tag {
-                BlockTag::Hatch(_) => (),
+                BlockTag::Hatch(_) | BlockTag::Plate { .. } | BlockTag::Gate(true) => (),
+                BlockTag::Fire { health, .. } if health <= 0. => (),
+                BlockTag::Spikes { .. } => {
+                    if obst.tag.is_hazard() {
+                        for e in room.enemies.iter_mut().filter(|e| e.get_layer().is_grounded()) {
+                            if obst.get_bbox(sw, sh).contains(e.get_pos()) { e.damage_over_time(SPIKES_ENEMY_DAMAGE * delta_time); }
+                        }
+                    }
+                },
                 _ => {
-                    for e in room.enemies.iter_mut() {
+                    for e in room.enemies.iter_mut().filter(|e| !e.is_anchored() && e.get_layer().is_blocked_by(&o.get_tag())) {
                         if dynamic_circle_vs_rect(&e.get_bcircle(sw, sh), &o.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct, delta_time) {
                             e.set_pos(e.get_pos() - cn.normalize() * ct);
+                            if let BlockTag::Fire { .. } = o.get_tag() { e.damage_over_time(FIRE_ENEMY_DAMAGE * delta_time); }
                         }
                     }
 
@@ -183,13 +210,27 @@ impl PlayScene {
             }
         }
 
+        for e in room.enemies.iter_mut().filter(|e| !e.is_anchored() && e.get_layer() == MovementLayer::Phasing) {
+            let (bw, bh) = (sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32);
+            e.set_pos(e.get_pos().clamp(Vec2::new(bw, bh), Vec2::new(sw - bw, sh - bh)));
+        }
+
+        if let Some((idx, dir)) = pushed_rock {
+            room.push_rock(idx, dir, sw, sh);
+        }
+
         if next_level {
-            if self.config.borrow().level > MAX_LEVEL {
+            let level = self.config.borrow().level;
+            if level > self.config.borrow().best_depth {
+                self.config.borrow_mut().best_depth = level;
+                if let Err(e) = save_best_depth(ctx, level) { eprintln!("Could not save the best depth: {}", e); }
+            }
+            if level > MAX_LEVEL && !self.config.borrow().endless {
                 self.config.borrow_mut().current_state = State::Victory;
                 self.config.borrow_mut().assets.audio.get_mut("victory_sound").unwrap().play(ctx)?;
-                return Ok(());
             }
-            self.dungeon = Dungeon::generate_dungeon((sw, sh), self.config.borrow().level);
+            // The next floor is generated on victory as well, so "Continue into endless" picks up right there.
+            self.dungeon = Dungeon::generate_dungeon((sw, sh), self.config.borrow().level, &self.config.borrow().bestiary)?;
             self.cur_room = Dungeon::get_start_room_coords();
             self.player.props.pos = Vec2::new(sw / 2., sh / 2.).into();
         }
@@ -201,18 +242,36 @@ impl PlayScene {
     fn handle_shot_collisions(&mut self, ctx: &mut Context, _delta_time: f32) -> GameResult {
         let (sw, sh) = (self.config.borrow().screen_width, self.config.borrow().screen_height);
         let room = self.dungeon.get_room_mut(self.cur_room)?.unwrap();
+        let mut extinguished = Vec::new();
 
         room.shots = room.shots.clone().into_iter().filter(|s| {
             match s.tag {
                 ShotTag::Player => {
-                    for enemy in room.enemies.iter_mut() {
-                        let (mut vel1, mut vel2) = (Vec2::ZERO, Vec2::ZERO);
-                        if dynamic_circle_vs_circle(&s.get_bcircle(sw, sh), &s.get_velocity(), &enemy.get_bcircle(sw, sh), &enemy.get_velocity(), &mut vel1, &mut vel2, _delta_time) {
-                            let _ = self.config.borrow_mut().assets.audio.get_mut("bubble_pop_sound").unwrap().play(ctx);
-                            enemy.damage(s.damage);
-                            enemy.set_velocity(enemy.get_velocity() + vel2);
-                            return false;
+                    let shot = s.get_bcircle(sw, sh);
+                    let mut hits = Vec::new();
+                    for (i, enemy) in room.enemies.iter().enumerate() {
+                        for (n, (shield, spin)) in enemy.get_shields(sw, sh).into_iter().enumerate() {
+                            hits.push((i, Some(n), shield, enemy.get_velocity() + spin * _delta_time));
+                        }
+                        if enemy.is_hittable() { hits.push((i, None, enemy.get_bcircle(sw, sh), enemy.get_velocity())); }
+                    }
+
+                    let first = hits.into_iter()
+                        .filter(|(_, _, c, _)| circle_vs_circle(&shot, c))
+                        .min_by(|a, b| contact_distance(&shot, &s.get_velocity(), &a.2).total_cmp(&contact_distance(&shot, &s.get_velocity(), &b.2)));
+                    if let Some((i, shield, c, vel)) = first {
+                        let _ = self.config.borrow_mut().assets.audio.get_mut("bubble_pop_sound").unwrap().play(ctx);
+                        let enemy = &mut room.enemies[i];
+                        match shield {
+                            Some(n) => enemy.damage_shield(n, s.damage),
+                            None => {
+                                let (mut vel1, mut vel2) = (Vec2::ZERO, Vec2::ZERO);
+                                dynamic_circle_vs_circle(&shot, &s.get_velocity(), &c, &vel, &mut vel1, &mut vel2, _delta_time);
+                                enemy.damage(s.damage);
+                                enemy.set_velocity(enemy.get_velocity() + vel2);
+                            },
                         }
+                        return false;
                     }
                 },
                 ShotTag::Enemy => {
@@ -224,11 +283,25 @@ impl PlayScene {
                 },
             };
 
-            for obst in room.obstacles.iter() {
+            for (idx, obst) in room.obstacles.iter_mut().enumerate() {
                 let (mut cp, mut cn) = (Vec2::ZERO, Vec2::ZERO);
                 let mut ct = 0.;
                 match obst.get_tag() {
-                    BlockTag::Hatch(_) | BlockTag::Spikes => (),
+                    BlockTag::Hatch(_) | BlockTag::Spikes { .. } | BlockTag::Plate { .. } | BlockTag::Gate(true) => (),
+                    BlockTag::Fire { health, is_shooter, shoot_timeout } => if health > 0. && s.tag == ShotTag::Player
+                        && dynamic_circle_vs_rect(&s.get_bcircle(sw, sh), &obst.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct, _delta_time) {
+                        obst.as_any_mut().downcast_mut::<Block>().unwrap().tag = BlockTag::Fire { health: health - s.damage, is_shooter, shoot_timeout };
+                        if health - s.damage <= 0. { extinguished.push(idx); }
+                        let _ = self.config.borrow_mut().assets.audio.get_mut("bubble_pop_sound").unwrap().play(ctx);
+                        return false;
+                    },
+                    BlockTag::Switch { channel, is_on } => if dynamic_circle_vs_rect(&s.get_bcircle(sw, sh), &obst.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct, _delta_time) {
+                        if s.tag == ShotTag::Player {
+                            obst.as_any_mut().downcast_mut::<Block>().unwrap().tag = BlockTag::Switch { channel, is_on: !is_on };
+                        }
+                        let _ = self.config.borrow_mut().assets.audio.get_mut("bubble_pop_sound").unwrap().play(ctx);
+                        return false;
+                    },
                     _ => if dynamic_circle_vs_rect(&s.get_bcircle(sw, sh), &obst.get_bbox(sw, sh), &mut cp, &mut cn, &mut ct, _delta_time) {
                         let _ = self.config.borrow_mut().assets.audio.get_mut("bubble_pop_sound").unwrap().play(ctx);
                         return false;
@@ -238,6 +311,26 @@ impl PlayScene {
             true
         }).collect();
 
+        for idx in extinguished {
+            room.extinguish_fire(idx, sw, sh);
+        }
+
+        Ok(())
+    }
+
+    /// Covers everything but a circle around the player, the stroke is wide enough to reach the screen corners.
+    ///
+    fn draw_darkness(&self, ctx: &mut Context) -> GameResult {
+        let (sw, sh) = (self.config.borrow().screen_width, self.config.borrow().screen_height);
+        let radius = sh * DARKNESS_RADIUS;
+        let width = 2. * Vec2::new(sw, sh).length();
+
+        let darkness = MeshBuilder::new()
+            .circle(DrawMode::stroke(width), self.player.props.pos, radius + width / 2., 1., Color::new(0., 0., 0., 0.95))?
+            .circle(DrawMode::stroke(radius * 0.2), self.player.props.pos, radius * 0.9, 1., Color::new(0., 0., 0., 0.5))?
+            .build(ctx)?;
+        graphics::draw(ctx, &darkness, DrawParam::default())?;
+
         Ok(())
     }
 
@@ -245,10 +338,13 @@ impl PlayScene {
         let (sw, sh) = (self.config.borrow().screen_width, self.config.borrow().screen_height);
         let room = self.dungeon.get_room_mut(self.cur_room)?.unwrap();
 
-        for e in room.enemies.iter_mut() {
+        for e in room.enemies.iter_mut().filter(|e| e.is_hittable()) {
             if circle_vs_circle(&e.get_bcircle(sw, sh), &self.player.get_bcircle(sw, sh)) {
-                resolve_environment_collision(&mut **e, &mut self.player, sw, sh, _delta_time);
-                self.player.damage(e.get_damage());
+                match e.is_anchored() {
+                    true => resolve_anchored_collision(&**e, &mut self.player, sw, sh),
+                    false => resolve_environment_collision(&mut **e, &mut self.player, sw, sh, _delta_time),
+                }
+                if e.blocks_clear() && e.get_damage() > 0. { self.player.damage(e.get_damage()); }
             }
         }
 
@@ -272,6 +368,8 @@ impl PlayScene {
 
 impl Scene for PlayScene {
     fn update(&mut self, ctx: &mut Context, delta_time: f32) -> GameResult {
+        if self.map.is_open { return Ok(()); }
+
         self.handle_input(ctx)?;
 
         self.handle_block_collisions(ctx, delta_time)?;
@@ -300,8 +398,12 @@ impl Scene for PlayScene {
 
         self.player.draw(ctx, &mut self.config.borrow_mut())?;
 
+        if self.dungeon.get_curses().contains(&Curse::Darkness) { self.draw_darkness(ctx)?; }
+
         self.overlay.draw(ctx, &mut self.config.borrow_mut())?;
 
+        if self.map.is_open { self.map.draw(ctx, &mut self.config.borrow_mut())?; }
+
         Ok(())
     }
 
@@ -310,12 +412,22 @@ impl Scene for PlayScene {
 
         match _keycode {
             KeyCode::Escape => self.config.borrow_mut().current_state = State::PauseMenu,
+            KeyCode::Tab if !_repeat => {
+                self.map.update_vars(&self.player, &self.dungeon, self.cur_room);
+                self.map.is_open = !self.map.is_open;
+            },
+            KeyCode::Equals | KeyCode::NumpadAdd if self.map.is_open => self.map.change_zoom(1.),
+            KeyCode::Minus | KeyCode::NumpadSubtract if self.map.is_open => self.map.change_zoom(-1.),
             _ => (),
         };
 
         self.config.borrow_mut().previous_state = cur;
     }
 
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        if self.map.is_open { self.map.change_zoom(y.signum()); }
+    }
+
     fn get_conf(&self) -> Option<Ref<Config>> { Some(self.config.borrow()) }
 
     fn get_conf_mut(&mut self) -> Option<RefMut<Config>> { Some(self.config.borrow_mut()) }
@@ -574,6 +686,7 @@ impl Scene for PauseMenuScene {
 pub struct DeadScene {
     config: Rc<RefCell<Config>>,
     ui_elements: Vec<Box<dyn UIElement>>,
+    depth: TextSprite,
 }
 
 impl DeadScene {
@@ -611,9 +724,16 @@ impl DeadScene {
             }),
         ];
 
+        let depth = TextSprite {
+            pos: Point2 { x: 0.5, y: 0.4},
+            color: Color::WHITE,
+            ..Default::default()
+        };
+
         Self {
             config,
             ui_elements,
+            depth,
         }
     }
 }
@@ -624,6 +744,9 @@ impl Scene for DeadScene {
             e.update(ctx, &mut self.config.borrow_mut())?;
         }
 
+        let (level, best_depth) = (self.config.borrow().level, self.config.borrow().best_depth);
+        self.depth.text = format!("DEPTH {}   BEST {}", level, best_depth);
+
         Ok(())
     }
 
@@ -642,6 +765,7 @@ impl Scene for DeadScene {
         for e in self.ui_elements.iter_mut() {
             e.draw(ctx, &mut self.config.borrow_mut())?;
         }
+        self.depth.draw(ctx, &mut self.config.borrow_mut())?;
 
         Ok(())
     }
@@ -1028,7 +1152,7 @@ impl VictoryScene {
                 ..Default::default()
             }),
             Box::new(Button {
-                pos: Point2 { x: 0.5, y: 0.5},
+                pos: Point2 { x: 0.5, y: 0.45},
                 action: ButtonAction::ChangeState(Some(State::New)),
                 tag: UIElementTag::State,
                 text: Some(TextSprite {
@@ -1039,7 +1163,19 @@ impl VictoryScene {
                 ..Default::default()
             }),
             Box::new(Button {
-                pos: Point2 { x: 0.5, y: 0.7},
+                pos: Point2 { x: 0.5, y: 0.6},
+                width: 0.5,
+                action: ButtonAction::ChangeState(Some(State::Endless)),
+                tag: UIElementTag::State,
+                text: Some(TextSprite {
+                    text: String::from("Continue into endless"),
+                    font: *config.borrow().assets.fonts.get("enigma").unwrap(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            Box::new(Button {
+                pos: Point2 { x: 0.5, y: 0.75},
                 action: ButtonAction::ChangeState(Some(State::Quit)),
                 tag: UIElementTag::State,
                 text: Some(TextSprite {
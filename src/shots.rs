@@ -57,6 +57,10 @@ impl Actor for Shot {
         Ok(())
     }
 
+    fn get_props(&self) -> &ActorProps { &self.props }
+
+    fn get_props_mut(&mut self) -> &mut ActorProps { &mut self.props }
+
     fn get_pos(&self) -> Vec2 { self.props.pos.into() }
 
     fn get_scale(&self) -> Vec2 { self.props.scale }
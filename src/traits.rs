@@ -16,10 +16,25 @@ use crate::{
     utils::*,
     player::*,
     shots::Shot,
+    creep::Creep,
     dungeon::BlockTag,
+    pathfinding::{FlowField, MovementLayer, required_clearance},
+    flocking::AllyGrid,
 };
 use rand::{thread_rng, Rng};
 
+/// What an actor sees of its room while acting.
+///
+#[derive(Clone, Copy)]
+pub struct ActContext<'a> {
+    pub sw: f32,
+    pub sh: f32,
+    pub flow: &'a FlowField,
+    pub obstacles: &'a Vec<Box<dyn Stationary>>,
+    pub allies: &'a AllyGrid,
+    pub player: &'a Player,
+}
+
 pub trait Actor: std::fmt::Debug {
     fn update(&mut self, _ctx: &mut Context, _config: &mut Config, _delta_time: f32) -> GameResult { Ok(()) }
 
@@ -70,6 +85,12 @@ pub trait Actor: std::fmt::Debug {
         Vec2::new(bbox.w / image.w, bbox.h / image.h)
     }
 
+    /// The state every actor keeps, actors read and write their surface through it.
+    ///
+    fn get_props(&self) -> &ActorProps;
+
+    fn get_props_mut(&mut self) -> &mut ActorProps;
+
     fn get_pos(&self) -> Vec2;
 
     fn get_scale(&self) -> Vec2;
@@ -80,6 +101,10 @@ pub trait Actor: std::fmt::Debug {
 
     fn get_forward(&self) -> Vec2;
 
+    fn get_surface(&self) -> Surface { self.get_props().surface }
+
+    fn get_slow(&self) -> f32 { self.get_props().slow }
+
     fn set_pos(&mut self, _new_pos: Vec2) {}
 
     fn set_scale(&mut self, _new_scale: Vec2) {}
@@ -90,7 +115,14 @@ pub trait Actor: std::fmt::Debug {
 
     fn set_forward(&mut self, _new_forward: Vec2) {}
 
+    fn set_surface(&mut self, new_surface: Surface) { self.get_props_mut().surface = new_surface; }
+
+    fn set_slow(&mut self, new_slow: f32) { self.get_props_mut().slow = new_slow; }
+
     fn velocity_lerp(&mut self, _delta_time: f32, speed: f32, decay: f32, acceleration: f32) {
+        let (speed_mod, decay_mod, acceleration_mod) = self.get_surface().modifiers();
+        let (speed, decay, acceleration) = (speed * speed_mod * self.get_slow(), decay * decay_mod, acceleration * acceleration_mod);
+
         self.set_translation(self.get_translation().clamp_length_max(1.));
         self.set_velocity(self.get_velocity() + self.get_translation() * acceleration * _delta_time);
         if self.get_translation().length() == 0. { self.set_velocity(self.get_velocity() / decay.clamp(_delta_time * (1. + 1e-2), f32::INFINITY) * _delta_time) }
@@ -98,7 +130,51 @@ pub trait Actor: std::fmt::Debug {
         if self.get_velocity().length() > speed && speed > 0. { self.set_velocity(self.get_velocity().clamp_length_max(speed)); }
     }
 
-    fn act(&mut self, _sw: f32, _sh: f32, _grid: &[[i32; ROOM_WIDTH]], _obstacles: &Vec<Box<dyn Stationary>>, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult { Ok(()) }
+    fn act(&mut self, _act: &ActContext, _shots: &mut Vec<Shot>) -> GameResult { Ok(()) }
+
+    fn leave_creep(&mut self) -> Option<Creep> { None }
+
+    /// Actors this one asked for while acting, the room adds them once every actor has acted.
+    ///
+    fn take_spawns(&mut self, _sw: f32, _sh: f32) -> Vec<Box<dyn Actor>> { Vec::new() }
+
+    /// Actors that take the place of this one once it is dead, they are added to the room it died in.
+    ///
+    fn on_death(&mut self, _grid: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], _sw: f32, _sh: f32) -> Vec<Box<dyn Actor>> { Vec::new() }
+
+    /// Whether shots, the player and hazards can touch this actor right now.
+    ///
+    fn is_hittable(&self) -> bool { true }
+
+    /// Anchored actors are never pushed, collisions move whatever runs into them instead.
+    ///
+    fn is_anchored(&self) -> bool { false }
+
+    /// Whether the room stays locked while this actor is alive.
+    ///
+    fn blocks_clear(&self) -> bool { true }
+
+    /// Called on every actor left in a room once it is cleared.
+    ///
+    fn shut_off(&mut self) {}
+
+    /// Bounding circles of the blockers that shield this actor from player shots, with how fast they circle it per second.
+    ///
+    fn get_shields(&self, _sw: f32, _sh: f32) -> Vec<((Vec2Wrap, f32), Vec2)> { Vec::new() }
+
+    /// Damages the `n`-th shield returned by `get_shields`.
+    ///
+    fn damage_shield(&mut self, _n: usize, _dmg: f32) {}
+
+    /// Actors on lower layers are drawn first, so the ones above cover them.
+    ///
+    fn draw_layer(&self) -> i32 { 0 }
+
+    fn get_layer(&self) -> MovementLayer { MovementLayer::Ground }
+
+    fn get_clearance(&self, sw: f32, sh: f32) -> u32 { required_clearance(self.get_bcircle(sw, sh).1, sw, sh) }
+
+    fn flocks(&self) -> bool { false }
 
     fn get_health(&self) -> f32; 
 
@@ -106,6 +182,10 @@ pub trait Actor: std::fmt::Debug {
 
     fn damage(&mut self, _dmg: f32) {}
 
+    /// Damage from hazards the actor stands in, it is added up and dealt once every `HAZARD_TICK` seconds.
+    ///
+    fn damage_over_time(&mut self, dmg: f32) { self.damage(dmg); }
+
     fn get_damage(&self) -> f32 { 0. }
 
     fn get_tag(&self) -> ActorTag;
@@ -120,6 +200,8 @@ pub trait Stationary: std::fmt::Debug {
 
     fn draw(&self, ctx: &mut Context, _config: &mut Config) -> GameResult;
 
+    fn act(&mut self, _sw: f32, _sh: f32, _shots: &mut Vec<Shot>, _player: &Player) -> GameResult { Ok(()) }
+
     fn draw_bbox(&self, ctx: &mut Context, screen: (f32, f32)) -> GameResult {
         let (sw, sh) = screen;
         let bbox = self.get_bbox(sw, sh);
@@ -191,6 +273,8 @@ pub trait Scene {
 
     fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32, _dx: f32, _dy: f32) {}
 
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {}
+
     fn get_ui_elements(&self) -> Option<&Vec<Box<dyn UIElement>>> { None }
 
     fn get_ui_elements_mut(&mut self) -> Option<&mut Vec<Box<dyn UIElement>>> { None }  
@@ -253,18 +337,10 @@ pub trait UIElement {
 }
 
 pub trait Chaser: Actor {
-    fn chase(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, grid: &[[i32; ROOM_WIDTH]], player: &Player);
-
-    fn find_path(&mut self, grid: &[[i32; ROOM_WIDTH]], sw: f32, sh: f32) -> Vec2 {
-        let (mut i, mut j) = pos_to_room_coords(self.get_pos(), sw, sh);
+    fn chase(&mut self, sw: f32, sh: f32, obstacles: &Vec<Box<dyn Stationary>>, flow: &FlowField, player: &Player);
 
-        if      i > 0               && grid[i - 1][j] > grid[i][j] { i -= 1; }
-        else if j > 0               && grid[i][j - 1] > grid[i][j] { j -= 1; }
-        else if j < ROOM_WIDTH - 1  && grid[i][j + 1] > grid[i][j] { j += 1; }
-        else if i < ROOM_HEIGHT - 1 && grid[i + 1][j] > grid[i][j] { i += 1; }
-        else { return self.get_pos(); }
-
-        room_coords_to_pos(i, j, sw, sh)
+    fn find_path(&mut self, flow: &FlowField, sw: f32, sh: f32) -> Vec2 {
+        self.get_pos() + flow.steer(self.get_pos(), sw, sh)
     }
 }
 
@@ -286,6 +362,12 @@ pub trait Wanderer: Actor {
         if j < ROOM_WIDTH - 1  && grid[i][j + 1] >= 0 { dirs.push(Vec2::X); }
         if i < ROOM_HEIGHT - 1 && grid[i + 1][j] >= 0 { dirs.push(Vec2::Y); }
 
+        if dirs.is_empty() {
+            let center = room_coords_to_pos(ROOM_HEIGHT / 2, ROOM_WIDTH / 2, sw, sh);
+            self.set_translation((center - self.get_pos()).normalize_or_zero());
+            return;
+        }
+
         if (self.get_change_direction_cooldown() == 0. && thread_rng().gen_bool(0.8)) || !dirs.contains(&self.get_translation()) {
             let dir = thread_rng().gen_range(0..dirs.len());
             self.set_translation(dirs[dir]);
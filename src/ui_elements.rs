@@ -14,7 +14,7 @@ use crate::{
     traits::*,
     consts::*,
     player::*,
-    dungeon::{Dungeon, RoomState},
+    dungeon::{Dungeon, RoomState, RoomTag, Curse},
     items::*,
 };
 
@@ -207,12 +207,77 @@ impl UIElement for Button {
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
 }
 
+/// What the map widgets know about a single room of the dungeon.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct MapRoom {
+    pub state: RoomState,
+    pub tag: RoomTag,
+    pub has_loot: bool,
+}
+
+impl MapRoom {
+    pub fn from_dungeon(dungeon: &Dungeon) -> Vec<Vec<Option<MapRoom>>> {
+        dungeon.get_grid().iter().map(|r| {
+            r.iter().map(|c| {
+                c.as_ref().map(|room| MapRoom {
+                    state: room.state,
+                    tag: room.tag,
+                    has_loot: room.has_loot(),
+                })
+            }).collect()
+        }).collect()
+    }
+
+    /// Icons are shown once the room is discovered, a compass shows the icons of the special rooms right away.
+    ///
+    pub fn is_icon_visible(&self, reveal_icons: bool) -> bool {
+        self.state != RoomState::Undiscovered || (reveal_icons && matches!(self.tag, RoomTag::Boss | RoomTag::Item))
+    }
+}
+
+/// Draws the icon of the room tag centered in the given rect, rooms without a special tag have no icon.
+///
+pub fn draw_room_icon(ctx: &mut Context, conf: &mut Config, tag: RoomTag, rect: Rect) -> GameResult {
+    let sprite = match tag {
+        RoomTag::Boss => conf.assets.sprites.get("boss_weird_ball_base").unwrap(),
+        RoomTag::Item => conf.assets.sprites.get("item_pedestal").unwrap(),
+        _ => return Ok(()),
+    };
+    let dims = sprite.dimensions();
+    let size = f32::min(rect.w, rect.h) * 0.7;
+
+    let draw_params = DrawParam::default()
+        .dest([rect.x + rect.w / 2., rect.y + rect.h / 2.])
+        .scale([size / dims.w, size / dims.h])
+        .offset([0.5, 0.5]);
+    graphics::draw(ctx, sprite, draw_params)?;
+
+    Ok(())
+}
+
+/// Marks a room that still holds an untaken item or pickup.
+///
+pub fn draw_loot_marker(ctx: &mut Context, rect: Rect) -> GameResult {
+    let r = f32::min(rect.w, rect.h) * 0.12;
+    let marker = MeshBuilder::new()
+        .circle(DrawMode::fill(), [rect.x + rect.w - r * 1.5, rect.y + r * 1.5], r, 0.5, Color::YELLOW)?
+        .circle(DrawMode::stroke(1.), [rect.x + rect.w - r * 1.5, rect.y + r * 1.5], r, 0.5, Color::BLACK)?
+        .build(ctx)?;
+    graphics::draw(ctx, &marker, DrawParam::default())?;
+
+    Ok(())
+}
+
 pub struct Minimap {
     pub pos: Point2<f32>,
     pub width: f32,
     pub height: f32,
     pub cur_room: (usize, usize),
-    pub rooms_state: Vec<Vec<RoomState>>,
+    pub rooms: Vec<Vec<Option<MapRoom>>>,
+    pub is_lost: bool,
+    pub reveal_icons: bool,
+    pub reveal_layout: bool,
 }
 
 impl UIElement for Minimap {
@@ -235,26 +300,38 @@ impl UIElement for Minimap {
 
         for r in 0..DUNGEON_GRID_ROWS {
             for c in 0..DUNGEON_GRID_COLS {
+                if self.is_lost && self.cur_room != (r, c) { continue; }
+
+                let room = match self.rooms[r][c] {
+                    Some(room) => room,
+                    None => continue,
+                };
+                let rect = Rect::new(pos.x + (c as f32) * rw, pos.y + (r as f32) * rh, rw, rh);
+
                 room_rect = MeshBuilder::new()
                     .rectangle( 
                         DrawMode::fill(),
-                        Rect::new(pos.x + (c as f32) * rw, pos.y + (r as f32) * rh, rw, rh),
+                        rect,
                         Color::BLACK,
                     )?
                     .rounded_rectangle(
                         DrawMode::fill(),
-                        Rect::new(pos.x + (c as f32) * rw, pos.y + (r as f32) * rh, rw, rh),
+                        rect,
                         8.,
                         Color::WHITE,
                     )?
                     .build(ctx)?;
 
-                match self.rooms_state[r][c] {
+                match room.state {
                     _ if self.cur_room == (r, c) => graphics::draw(ctx, &room_rect, DrawParam::default().color(Color::WHITE))?,
                     RoomState::Discovered => graphics::draw(ctx, &room_rect, DrawParam::default().color(Color::new(0.3, 0.3, 0.3, 1.)))?,
                     RoomState::Cleared => graphics::draw(ctx, &room_rect, DrawParam::default().color(Color::new(0.6, 0.6, 0.6, 1.)))?,
+                    RoomState::Undiscovered if self.reveal_layout => graphics::draw(ctx, &room_rect, DrawParam::default().color(Color::new(0.15, 0.15, 0.15, 1.)))?,
                     _ => (),
                 }
+
+                if room.is_icon_visible(self.reveal_icons) { draw_room_icon(ctx, conf, room.tag, rect)?; }
+                if room.has_loot && room.state != RoomState::Undiscovered { draw_loot_marker(ctx, rect)?; }
             }
         }
         Ok(())
@@ -271,6 +348,151 @@ impl UIElement for Minimap {
     fn as_any_mut(&mut self) -> &mut dyn Any { self }
 }
 
+/// Full-screen version of the minimap, centered on the current room and zoomable.
+///
+pub struct FullMap {
+    pub is_open: bool,
+    pub zoom: f32,
+    pub cur_room: (usize, usize),
+    pub rooms: Vec<Vec<Option<MapRoom>>>,
+    pub level: u32,
+    pub curses: Vec<Curse>,
+    pub reveal_icons: bool,
+    pub reveal_layout: bool,
+}
+
+impl Default for FullMap {
+    fn default() -> Self {
+        Self {
+            is_open: false,
+            zoom: 1.,
+            cur_room: (0, 0),
+            rooms: vec![vec![None; DUNGEON_GRID_COLS]; DUNGEON_GRID_ROWS],
+            level: 0,
+            curses: Vec::new(),
+            reveal_icons: false,
+            reveal_layout: false,
+        }
+    }
+}
+
+impl FullMap {
+    pub fn update_vars(&mut self, player: &Player, dungeon: &Dungeon, cur_room: (usize, usize)) {
+        self.cur_room = cur_room;
+        self.rooms = MapRoom::from_dungeon(dungeon);
+        self.level = dungeon.get_level();
+        self.curses = dungeon.get_curses().to_vec();
+        self.reveal_icons = player.has_compass;
+        self.reveal_layout = player.has_map;
+    }
+
+    pub fn change_zoom(&mut self, change: f32) {
+        self.zoom = (self.zoom + change * MAP_ZOOM_STEP).clamp(MAP_ZOOM_MIN, MAP_ZOOM_MAX);
+    }
+
+    fn is_room_visible(&self, (r, c): (usize, usize)) -> bool {
+        if self.curses.contains(&Curse::Lost) && self.cur_room != (r, c) { return false; }
+
+        match self.rooms[r][c] {
+            Some(room) => room.state != RoomState::Undiscovered || self.reveal_layout,
+            None => false,
+        }
+    }
+}
+
+impl UIElement for FullMap {
+    fn update(&mut self, _ctx: &mut Context, _conf: &mut Config) -> GameResult { Ok(()) }
+
+    fn draw(&mut self, ctx: &mut Context, conf: &mut Config) -> GameResult {
+        let (sw, sh) = (conf.screen_width, conf.screen_height);
+        let backdrop = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., sw, sh), Color::new(0., 0., 0., 0.85))?;
+        graphics::draw(ctx, &backdrop, DrawParam::default())?;
+
+        let cell = f32::min(sw / DUNGEON_GRID_COLS as f32, sh / DUNGEON_GRID_ROWS as f32) * 0.8 * self.zoom;
+        let origin = Vec2::new(sw / 2., sh / 2.) - (Vec2::new(self.cur_room.1 as f32, self.cur_room.0 as f32) + 0.5) * cell;
+        let room_rect = |r: usize, c: usize| Rect::new(origin.x + c as f32 * cell + cell * 0.1, origin.y + r as f32 * cell + cell * 0.1, cell * 0.8, cell * 0.8);
+
+        let mut doors = MeshBuilder::new();
+        let mut has_doors = false;
+        for r in 0..DUNGEON_GRID_ROWS {
+            for c in 0..DUNGEON_GRID_COLS {
+                if !self.is_room_visible((r, c)) { continue; }
+
+                let rect = room_rect(r, c);
+                if c + 1 < DUNGEON_GRID_COLS && self.is_room_visible((r, c + 1)) {
+                    doors.rectangle(DrawMode::fill(), Rect::new(rect.x + rect.w, rect.y + rect.h * 0.4, cell * 0.2, rect.h * 0.2), Color::new(0.5, 0.5, 0.5, 1.))?;
+                    has_doors = true;
+                }
+                if r + 1 < DUNGEON_GRID_ROWS && self.is_room_visible((r + 1, c)) {
+                    doors.rectangle(DrawMode::fill(), Rect::new(rect.x + rect.w * 0.4, rect.y + rect.h, rect.w * 0.2, cell * 0.2), Color::new(0.5, 0.5, 0.5, 1.))?;
+                    has_doors = true;
+                }
+            }
+        }
+        if has_doors {
+            let doors = doors.build(ctx)?;
+            graphics::draw(ctx, &doors, DrawParam::default())?;
+        }
+
+        for r in 0..DUNGEON_GRID_ROWS {
+            for c in 0..DUNGEON_GRID_COLS {
+                if !self.is_room_visible((r, c)) { continue; }
+
+                let room = self.rooms[r][c].unwrap();
+                let rect = room_rect(r, c);
+                let color = match room.state {
+                    RoomState::Undiscovered => Color::new(0.15, 0.15, 0.15, 1.),
+                    RoomState::Discovered => Color::new(0.3, 0.3, 0.3, 1.),
+                    RoomState::Cleared => Color::new(0.6, 0.6, 0.6, 1.),
+                };
+
+                let room_mesh = MeshBuilder::new()
+                    .rounded_rectangle(DrawMode::fill(), rect, 8., color)?
+                    .rounded_rectangle(DrawMode::stroke(2.), rect, 8., Color::WHITE)?
+                    .build(ctx)?;
+                graphics::draw(ctx, &room_mesh, DrawParam::default())?;
+
+                if room.is_icon_visible(self.reveal_icons) { draw_room_icon(ctx, conf, room.tag, rect)?; }
+                if room.has_loot && room.state != RoomState::Undiscovered { draw_loot_marker(ctx, rect)?; }
+            }
+        }
+
+        let player_mesh = Mesh::new_circle(ctx, DrawMode::fill(), [sw / 2., sh / 2.], cell * 0.12, 0.5, Color::RED)?;
+        graphics::draw(ctx, &player_mesh, DrawParam::default())?;
+
+        let mut title = TextSprite {
+            pos: Point2 { x: 0.5, y: 0.07 },
+            text: format!("FLOOR {}", self.level),
+            color: Color::WHITE,
+            ..Default::default()
+        };
+        title.draw(ctx, conf)?;
+
+        if !self.curses.is_empty() {
+            let mut curses = TextSprite {
+                pos: Point2 { x: 0.5, y: 0.93 },
+                text: self.curses.iter().map(|c| format!("Curse of the {:?}", c)).collect::<Vec<_>>().join("   "),
+                font_size: BUTTON_TEXT_FONT_SIZE * 0.5,
+                color: Color::new(0.8, 0.4, 1., 1.),
+                ..Default::default()
+            };
+            curses.draw(ctx, conf)?;
+        }
+
+        Ok(())
+    }
+
+    fn pos(&self, _sw: f32, _sh: f32) -> Point2<f32> { Point2 { x: 0., y: 0. } }
+
+    fn width(&self, _ctx: &mut Context, sw: f32) -> f32 { sw }
+
+    fn height(&self, _ctx: &mut Context, sh: f32) -> f32 { sh }
+
+    fn as_any(&self) -> &dyn Any { self }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
 pub struct HealthBar {
     pub pos: Point2<f32>,
     pub width: f32,
@@ -341,6 +563,8 @@ impl UIElement for ItemHolder {
             Some(tag) => match tag {
                 ItemTag::Passive(p) => match p {
                     ItemPassive::IncreaseMaxHealth(_) => conf.assets.sprites.get("poop_item"),
+                    ItemPassive::PukeTrail => conf.assets.sprites.get("shot_puke_base"),
+                    ItemPassive::Compass | ItemPassive::Map => None,
                 },
                 ItemTag::Active(a) => match a {
                     ItemActive::Heal(_) => conf.assets.sprites.get("heart_item"),
@@ -421,7 +645,15 @@ impl Overlay {
                 width: MINIMAP_SCALE,
                 height: MINIMAP_SCALE,
                 cur_room,
-                rooms_state: vec![vec![RoomState::Undiscovered; DUNGEON_GRID_COLS]; DUNGEON_GRID_ROWS],
+                rooms: vec![vec![None; DUNGEON_GRID_COLS]; DUNGEON_GRID_ROWS],
+                is_lost: false,
+                reveal_icons: false,
+                reveal_layout: false,
+            }),
+            Box::new(TextSprite {
+                pos: Point2 { x: DEPTH_TEXT_POS.0, y: DEPTH_TEXT_POS.1 },
+                color: Color::WHITE,
+                ..Default::default()
             }),
         ];
 
@@ -441,12 +673,13 @@ impl Overlay {
             }
             else if let Some(m) = e.as_any_mut().downcast_mut::<Minimap>() {
                 m.cur_room = cur_room;
-                m.rooms_state = dungeon.get_grid().iter().map(|r| {
-                    r.iter().map(|c| { 
-                        if let Some(room) = c { room.state }
-                        else { RoomState::Undiscovered }
-                    }).collect()
-                }).collect();
+                m.is_lost = dungeon.get_curses().contains(&Curse::Lost);
+                m.reveal_icons = player.has_compass;
+                m.reveal_layout = player.has_map;
+                m.rooms = MapRoom::from_dungeon(dungeon);
+            }
+            else if let Some(t) = e.as_any_mut().downcast_mut::<TextSprite>() {
+                t.text = format!("DEPTH {}", dungeon.get_level());
             }
             else if let Some(i) = e.as_any_mut().downcast_mut::<ItemHolder>() {
                 match player.item {
@@ -2,22 +2,28 @@ use ggez::{
     mint::Point2,
     graphics::{Rect, Color},
     GameError,
+    GameResult,
+    Context,
+    filesystem,
     audio::SoundSource,
     conf::FullscreenType,
 };
 use glam::f32::Vec2;
 use std::{
     fmt::{self, Display, Formatter},
+    io::{Read, Write},
     str::FromStr,
 };
 use crate::{
     traits::*,
     consts::*,
     assets::*,
+    bestiary::Bestiary,
 };
 
 pub struct Config {
     pub assets: Assets,
+    pub bestiary: Bestiary,
     pub screen_width: f32,
     pub screen_height: f32,
     pub window_width: f32,
@@ -29,6 +35,8 @@ pub struct Config {
     pub current_state: State,
     pub previous_state: State,
     pub level: u32,
+    pub endless: bool,
+    pub best_depth: u32,
 }
 
 #[derive(Clone, Copy, Hash, Debug)]
@@ -42,6 +50,7 @@ pub enum State {
     Dead,
     Transition,
     Victory,
+    Endless,
 }
 
 impl Display for State {
@@ -64,6 +73,7 @@ impl FromStr for State {
             "Dead" => State::Dead,
             "Transition" => State::Transition,
             "Victory" => State::Victory,
+            "Endless" => State::Endless,
             _ => return Err(Errors::StateParse(input.to_string())),
         };
         Ok(state)
@@ -82,6 +92,8 @@ pub enum Errors {
     UnknownRoomIndex(usize),
     UnknownGridCoords((usize, usize)),
     StateParse(String),
+    LayoutParse(String),
+    EnemyParse(String),
 }
 
 impl Display for Errors {
@@ -118,6 +130,29 @@ pub enum ActorState {
     Damaged,
 }
 
+/// Floor surface under an actor, it changes how fast the actor accelerates, stops and moves.
+///
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub enum Surface {
+    Normal,
+    Ice,
+    Sticky,
+    SpeedPad,
+}
+
+impl Surface {
+    /// Returns the `(speed, decay, acceleration)` multipliers for the surface.
+    ///
+    pub fn modifiers(&self) -> (f32, f32, f32) {
+        match self {
+            Surface::Normal => (1., 1., 1.),
+            Surface::Ice => SURFACE_ICE_MODIFIERS,
+            Surface::Sticky => SURFACE_STICKY_MODIFIERS,
+            Surface::SpeedPad => SURFACE_SPEED_PAD_MODIFIERS,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Copy)]
 pub enum ActorTag {
     Player,
@@ -131,6 +166,10 @@ pub struct ActorProps {
     pub translation: Vec2,
     pub forward: Vec2,
     pub velocity: Vec2,
+    pub surface: Surface,
+    /// Speed multiplier of the creep the actor stands in, 1 outside of creep.
+    ///
+    pub slow: f32,
 }
 
 impl Default for ActorProps {
@@ -141,6 +180,8 @@ impl Default for ActorProps {
             translation: Vec2::ZERO,
             forward: Vec2::ZERO,
             velocity: Vec2::ZERO,
+            surface: Surface::Normal,
+            slow: 1.,
         }
     }
 }
@@ -313,6 +354,16 @@ pub fn dynamic_circle_vs_circle(c1: &(Vec2Wrap, f32), c1_vel: &Vec2, c2: &(Vec2W
     true
 }
 
+/// How far `c1` travels along its velocity before it first touches `c2`, negative when it already went past that point.
+///
+pub fn contact_distance(c1: &(Vec2Wrap, f32), c1_vel: &Vec2, c2: &(Vec2Wrap, f32)) -> f32 {
+    let rel = c2.0.0 - c1.0.0;
+    let along = rel.dot(c1_vel.normalize_or_zero());
+    let reach = (c1.1 + c2.1).powi(2) - (rel.length_squared() - along * along);
+
+    along - f32::max(0., reach).sqrt()
+}
+
 pub fn mouse_relative_forward(target: Vec2, mouse: Point2<f32>, conf: &Config) -> Vec2 {
     let (sw, sh) = (conf.screen_width, conf.screen_height);
     let (ww, wh) = (conf.window_width, conf.window_height);
@@ -347,6 +398,15 @@ pub fn resolve_environment_collision(e1: &mut dyn Actor, e2: &mut dyn Actor, sw:
     }
 }
 
+/// Pushes `e2` out of `e1` on its own, for when `e1` can't be moved.
+///
+pub fn resolve_anchored_collision(e1: &dyn Actor, e2: &mut dyn Actor, sw: f32, sh: f32) {
+    let mut displace_vec = Vec2::ZERO;
+    if static_circle_vs_circle(&e1.get_bcircle(sw, sh), &e2.get_bcircle(sw, sh), &mut displace_vec) {
+        e2.set_pos(e2.get_pos() + displace_vec * 2.);
+    }
+}
+
 pub fn pos_to_room_coords(pos: Vec2, sw: f32, sh: f32) -> (usize, usize) {
     ((pos.y / sh * (ROOM_HEIGHT as f32)).floor() as usize, (pos.x / sw * (ROOM_WIDTH as f32)).floor() as usize)
 }
@@ -355,10 +415,43 @@ pub fn room_coords_to_pos(i: usize, j: usize, sw: f32, sh: f32) -> Vec2 {
     Vec2::new((j as f32 + 0.5) * sw / ROOM_WIDTH as f32, (i as f32 + 0.5) * sh / ROOM_HEIGHT as f32)
 }
 
+/// Keeps `pos` when its tile is free, otherwise moves it to the middle of the closest free tile.
+///
+pub fn snap_to_free_tile(pos: Vec2, grid: &[[i32; ROOM_WIDTH]; ROOM_HEIGHT], sw: f32, sh: f32) -> Vec2 {
+    let (i, j) = pos_to_room_coords(pos, sw, sh);
+    if i < ROOM_HEIGHT && j < ROOM_WIDTH && grid[i][j] >= 0 { return pos; }
+
+    (0..ROOM_HEIGHT * ROOM_WIDTH)
+        .map(|k| (k / ROOM_WIDTH, k % ROOM_WIDTH))
+        .filter(|&(i, j)| grid[i][j] >= 0)
+        .map(|(i, j)| room_coords_to_pos(i, j, sw, sh))
+        .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)))
+        .unwrap_or(pos)
+}
+
 pub fn invert_color(c: &Color) -> Color {
     Color::from_rgb_u32(!c.to_rgb_u32())
 }
 
+/// The deepest endless floor reached in earlier runs, 0 when none was saved yet.
+///
+pub fn load_best_depth(ctx: &Context) -> u32 {
+    let mut source = String::new();
+    match filesystem::open(ctx, BEST_DEPTH_FILE).map(|mut f| f.read_to_string(&mut source)) {
+        Ok(Ok(_)) => source.trim().parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Saves the best depth to the user data directory, so it is still there after the game is quit.
+///
+pub fn save_best_depth(ctx: &Context, depth: u32) -> GameResult {
+    let mut file = filesystem::create(ctx, BEST_DEPTH_FILE)?;
+    write!(file, "{}", depth)?;
+
+    Ok(())
+}
+
 pub fn change_scene(conf: &mut Config, new_state: Option<State>) {
     let cur = conf.current_state;
     let prev = conf.previous_state;
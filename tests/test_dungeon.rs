@@ -1,11 +1,20 @@
 use puker::{
     dungeon::*,
     consts::*,
+    puzzles::*,
+    player::*,
+    traits::*,
+    shots::*,
+    difficulty::*,
+    pathfinding::*,
+    bestiary::*,
 };
 use glam::f32::Vec2;
 
 const SCREEN: (f32, f32) = (DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT);
 
+fn bestiary() -> Bestiary { Bestiary::from_dir("resources/enemies").unwrap() }
+
 #[test]
 fn test_dungeon_consistency_checker() {
     let grid_bad = [[0, 0, 0, 0, 0, 0, 0, 0, 0],
@@ -33,7 +42,7 @@ fn test_dungeon_consistency_checker() {
 #[test]
 fn test_dungeon_generation_test() {
     for i in 1..5 {
-        let dungeon = Dungeon::generate_dungeon(SCREEN, i);
+        let dungeon = Dungeon::generate_dungeon(SCREEN, i, &bestiary()).unwrap();
         let mut rooms_count: i32 = 0;
         let mut specials_count: i32 = 0;
 
@@ -59,7 +68,7 @@ fn test_dungeon_generation_test() {
 
 #[test]
 fn test_dungeon_room_retrieval() {
-    let mut dungeon = Dungeon::generate_dungeon(SCREEN, 1);
+    let mut dungeon = Dungeon::generate_dungeon(SCREEN, 1, &bestiary()).unwrap();
     let start_room = Dungeon::get_start_room_coords();
 
     assert!(matches!(dungeon.get_room(start_room).unwrap(), Some(_)));
@@ -69,7 +78,7 @@ fn test_dungeon_room_retrieval() {
 
 #[test]
 fn test_dungeon_state_update() {
-    let mut dungeon = Dungeon::generate_dungeon(SCREEN, 1);
+    let mut dungeon = Dungeon::generate_dungeon(SCREEN, 1, &bestiary()).unwrap();
     let (i, j) = Dungeon::get_start_room_coords();
 
     dungeon.update_rooms_state((i, j)).unwrap();
@@ -82,7 +91,7 @@ fn test_dungeon_state_update() {
 
 #[test]
 fn test_room_target_distance_grid() {
-    let dungeon = Dungeon::generate_dungeon(SCREEN, 1);
+    let dungeon = Dungeon::generate_dungeon(SCREEN, 1, &bestiary()).unwrap();
     let grid = dungeon.get_room((3, 5)).unwrap().unwrap().get_target_distance_grid(Vec2::new(SCREEN.0 / 2., SCREEN.1 / 2.), SCREEN.0, SCREEN.1);
 
     let (mut i, mut j) = (1, 1);
@@ -97,3 +106,231 @@ fn test_room_target_distance_grid() {
 
     assert_eq!((4, 7), (i, j));
 }
+
+#[test]
+fn test_room_layout_wiring() {
+    let layouts = ROOM_LAYOUTS_MOB.iter()
+        .chain(ROOM_LAYOUTS_EMPTY)
+        .chain(ROOM_LAYOUTS_ITEM)
+        .chain(ROOM_LAYOUTS_BOSS);
+
+    for layout in layouts {
+        let lines = layout.trim().split('\n').map(|l| l.trim()).collect::<Vec<_>>();
+
+        assert!(lines[..ROOM_HEIGHT].iter().all(|l| l.len() == ROOM_WIDTH));
+        let (puzzle, _) = Room::parse_wiring(&lines[ROOM_HEIGHT..]).unwrap();
+        for wire in puzzle.wires {
+            let tiles = if wire.action == PuzzleAction::ToggleGates { "g" } else { "v" };
+            assert!(wire.targets.iter().all(|&(i, j)| tiles.contains(lines[i].as_bytes()[j] as char)));
+        }
+    }
+
+    assert!(Puzzle::parse(&["1 lever doors"]).is_err());
+    assert!(Puzzle::parse(&["1 plate doors", "1 switch gates 1,1"]).is_err());
+    assert!(Puzzle::parse(&["2 switch gates"]).is_err());
+    assert!(Puzzle::parse(&["1 plate doors 1,1"]).is_err());
+    assert!(Puzzle::parse(&["2 switch gates 1,1 9,0"]).is_err());
+}
+
+#[test]
+fn test_puzzle_update() {
+    let mut puzzle = Puzzle::parse(&["1 plate doors", "2 switch spikes 1,1", "4 plate spikes 1,1 2,2"]).unwrap();
+    let mut channels = [false; PUZZLE_CHANNELS];
+
+    assert!(!puzzle.is_solved());
+
+    channels[1] = true;
+    channels[2] = true;
+
+    assert_eq!(vec![(PuzzleAction::OpenDoors, true), (PuzzleAction::RetractSpikes, true)], puzzle.update(&channels));
+    assert!(puzzle.is_solved());
+    assert_eq!(puzzle.drives(PuzzleAction::RetractSpikes, (1, 1)), Some(true));
+    assert_eq!(puzzle.drives(PuzzleAction::RetractSpikes, (2, 2)), Some(false));
+    assert_eq!(puzzle.drives(PuzzleAction::RetractSpikes, (3, 3)), None);
+    assert_eq!(puzzle.drives(PuzzleAction::ToggleGates, (1, 1)), None);
+
+    channels = [false; PUZZLE_CHANNELS];
+
+    assert_eq!(vec![(PuzzleAction::RetractSpikes, false)], puzzle.update(&channels));
+    assert!(puzzle.is_solved());
+}
+
+#[test]
+fn test_spikes_cycle() {
+    let cycle = SpikesCycle::default();
+    let mut state = SpikesState::Retracted;
+    let mut total = 0.;
+
+    for _ in 0..3 {
+        total += cycle.duration(state);
+        state = cycle.next(state);
+    }
+
+    assert_eq!(state, SpikesState::Retracted);
+    assert_eq!(total, SPIKES_EXTENDED_TIME + SPIKES_WARNING_TIME + SPIKES_RETRACTED_TIME);
+    assert_eq!(cycle.next(SpikesState::Retracted), SpikesState::Warning);
+    assert_eq!(cycle.next(SpikesState::Warning), SpikesState::Extended);
+
+    let (puzzle, layout_cycle) = Room::parse_wiring(&["cycle 1 0.5 2.5", "1 plate doors"]).unwrap();
+    assert_eq!(puzzle.wires.len(), 1);
+    assert_eq!(layout_cycle, SpikesCycle { extended: 1., warning: 0.5, retracted: 2.5 });
+    assert!(Room::parse_wiring(&["cycle 1 0.5"]).is_err());
+    assert!(Room::parse_wiring(&["cycle 1 0 2"]).is_err());
+    assert!(Room::parse_wiring(&["cycle 1 0.5 2", "cycle 1 0.5 2"]).is_err());
+
+    assert!(BlockTag::Spikes { state: SpikesState::Extended, timer: 0., cycle: Some(cycle) }.is_hazard());
+    assert!(!BlockTag::Spikes { state: SpikesState::Warning, timer: 0., cycle: Some(cycle) }.is_hazard());
+    assert!(!BlockTag::Spikes { state: SpikesState::Retracted, timer: 0., cycle: None }.is_hazard());
+}
+
+#[test]
+fn test_fire_shooter() {
+    let player = Player::default();
+    let mut shots = Vec::new();
+    let mut fire = Block {
+        pos: (player.get_pos() + Vec2::X * FIRE_SHOOT_RANGE / 2.).into(),
+        scale: Vec2::splat(WALL_SCALE),
+        tag: BlockTag::Fire { health: FIRE_HEALTH, is_shooter: true, shoot_timeout: 0. },
+    };
+
+    fire.act(SCREEN.0, SCREEN.1, &mut shots, &player).unwrap();
+
+    assert_eq!(shots.len(), 1);
+    assert_eq!(shots[0].tag, ShotTag::Enemy);
+
+    fire.act(SCREEN.0, SCREEN.1, &mut shots, &player).unwrap();
+
+    assert_eq!(shots.len(), 1);
+    assert!(!fire.tag.is_floor());
+
+    fire.tag = BlockTag::Fire { health: 0., is_shooter: true, shoot_timeout: 0. };
+    fire.act(SCREEN.0, SCREEN.1, &mut shots, &player).unwrap();
+
+    assert_eq!(shots.len(), 1);
+    assert!(fire.tag.is_floor());
+}
+
+#[test]
+fn test_endless_generation() {
+    let pos = Vec2::new(SCREEN.0, SCREEN.1) / 2.;
+    let base = Room::spawn_enemy('b', pos, &Difficulty::for_level(MAX_LEVEL), &bestiary()).unwrap();
+    let deep = Room::spawn_enemy('b', pos, &Difficulty::for_level(MAX_LEVEL + 4), &bestiary()).unwrap();
+
+    assert!(deep.get_health() > base.get_health());
+    assert!(Room::spawn_enemy(' ', pos, &Difficulty::for_level(MAX_LEVEL), &bestiary()).is_none());
+
+    for level in 1..=MAX_LEVEL {
+        assert!(Dungeon::generate_dungeon(SCREEN, level, &bestiary()).unwrap().get_curses().is_empty());
+    }
+
+    let dungeon = Dungeon::generate_dungeon(SCREEN, MAX_LEVEL + 10, &bestiary()).unwrap();
+    let rooms_count = dungeon.get_grid().iter().flatten().filter(|r| r.is_some()).count();
+
+    assert_eq!(dungeon.get_level(), MAX_LEVEL + 10);
+    assert!(rooms_count <= DUNGEON_MAX_ROOMS);
+}
+
+#[test]
+fn test_difficulty_curve() {
+    assert_eq!(DIFFICULTY_CURVE.len(), MAX_LEVEL as usize);
+    assert_eq!(Difficulty::for_level(0), DIFFICULTY_CURVE[0]);
+
+    for level in 1..=MAX_LEVEL {
+        let difficulty = Difficulty::for_level(level);
+
+        assert_eq!(difficulty, DIFFICULTY_CURVE[level as usize - 1]);
+        assert!(difficulty.room_count.0 <= difficulty.room_count.1);
+        assert!(difficulty.room_count.1 <= DUNGEON_MAX_ROOMS);
+        assert_eq!(difficulty.extra_enemies, 0);
+    }
+
+    let last = Difficulty::for_level(MAX_LEVEL);
+    let deep = Difficulty::for_level(MAX_LEVEL + 3);
+
+    assert!(deep.enemy_health > last.enemy_health);
+    assert!(deep.boss_shoot_rate > last.boss_shoot_rate);
+    assert!(deep.curse_chance > last.curse_chance);
+    assert_eq!(deep.extra_enemies, 3);
+    assert!(Difficulty::for_level(MAX_LEVEL + 100).enemy_speed <= ENDLESS_SPEED_MAX);
+}
+
+#[test]
+fn test_flow_field() {
+    let mut obstacles = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
+    obstacles[3][4] = i32::MIN;
+    obstacles[4][3] = i32::MIN;
+
+    let (bw, bh) = (SCREEN.0 / ROOM_WIDTH as f32, SCREEN.1 / ROOM_HEIGHT as f32);
+    let target = Vec2::new(6.5 * bw, 6.5 * bh);
+    let mut flow = FlowField::new(&obstacles, target, 1, SCREEN.0, SCREEN.1);
+
+    assert_eq!(flow.get_direction((8, 8)), -Vec2::ONE.normalize());
+    assert_eq!(flow.get_direction((6, 6)), Vec2::ZERO);
+    assert_eq!(flow.get_grid()[6][6], i32::MAX);
+    assert_eq!(flow.get_grid()[3][4], i32::MIN);
+
+    // The gap between the two blocks is a corner, so it has to be walked around.
+    assert_ne!(flow.get_direction((3, 3)), Vec2::ONE.normalize());
+    assert_ne!(flow.get_direction((3, 3)), Vec2::ZERO);
+
+    assert!(!flow.update(&obstacles, target + Vec2::splat(bw / 4.), SCREEN.0, SCREEN.1));
+    assert!(flow.update(&obstacles, target + Vec2::X * bw, SCREEN.0, SCREEN.1));
+
+    obstacles[5][5] = i32::MIN;
+    assert!(flow.update(&obstacles, target + Vec2::X * bw, SCREEN.0, SCREEN.1));
+
+    let steer = flow.steer(Vec2::new(1.5 * bw, 1.5 * bh), SCREEN.0, SCREEN.1);
+    assert!((steer.length() - 1.).abs() < 1e-4);
+}
+
+#[test]
+fn test_movement_layers() {
+    let stone = BlockTag::Stone;
+    let spikes = BlockTag::Spikes { state: SpikesState::Extended, timer: 0., cycle: None };
+
+    assert!(MovementLayer::Ground.is_blocked_by(&stone));
+    assert!(!MovementLayer::Ground.is_blocked_by(&spikes));
+    assert!(!MovementLayer::Flying.is_blocked_by(&stone));
+    assert!(MovementLayer::Flying.is_blocked_by(&BlockTag::Wall));
+    assert!(!MovementLayer::Phasing.is_blocked_by(&BlockTag::Wall));
+    assert!(MovementLayer::Ground.is_grounded());
+    assert!(!MovementLayer::Flying.is_grounded());
+
+    let dungeon = Dungeon::generate_dungeon(SCREEN, 1, &bestiary()).unwrap();
+    let room = dungeon.get_room(Dungeon::get_start_room_coords()).unwrap().unwrap();
+    let ground = room.get_layer_grid(MovementLayer::Ground, SCREEN.0, SCREEN.1);
+    let flying = room.get_layer_grid(MovementLayer::Flying, SCREEN.0, SCREEN.1);
+    let phasing = room.get_layer_grid(MovementLayer::Phasing, SCREEN.0, SCREEN.1);
+
+    assert_eq!(ground, room.grid);
+    assert_eq!(flying[0][0], i32::MIN);
+    assert!(flying.iter().flatten().zip(ground.iter().flatten()).all(|(f, g)| *f == 0 || *g == i32::MIN));
+    assert!(phasing.iter().flatten().all(|c| *c == 0));
+}
+
+#[test]
+fn test_clearance_flow_field() {
+    let tile = f32::min(SCREEN.0 / ROOM_WIDTH as f32, SCREEN.1 / ROOM_HEIGHT as f32);
+    assert_eq!(required_clearance(tile * ENEMY_SCALE / 2., SCREEN.0, SCREEN.1), 1);
+    assert_eq!(required_clearance(tile * ENEMY_SCALE, SCREEN.0, SCREEN.1), 2);
+
+    // A wall through the middle of the room with a single tile gap in it.
+    let mut obstacles = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
+    for row in obstacles.iter_mut() { row[7] = i32::MIN; }
+    obstacles[4][7] = 0;
+
+    let clearance = clearance_grid(&obstacles);
+    assert_eq!(clearance[0][0], 1);
+    assert_eq!(clearance[4][7], 1);
+    assert_eq!(clearance[4][3], 4);
+    assert_eq!(clearance[4][7 - 1], 1);
+
+    let target = Vec2::new(SCREEN.0 * 0.8, SCREEN.1 / 2.);
+    let small = FlowField::new(&obstacles, target, 1, SCREEN.0, SCREEN.1);
+    let large = FlowField::new(&obstacles, target, 2, SCREEN.0, SCREEN.1);
+
+    assert!(small.get_grid()[4][3] > 0);
+    assert_eq!(large.get_clearance(), 2);
+    assert_eq!(large.get_grid()[4][7], i32::MIN);
+    assert_eq!(large.get_direction((4, 3)), Vec2::ZERO);
+}
@@ -6,12 +6,56 @@ use puker::{
     utils::*,
     shots::*,
     dungeon::*,
+    creep::*,
+    ai::*,
+    aiming::*,
+    flocking::*,
+    difficulty::Difficulty,
+    pathfinding::FlowField,
+    bestiary::*,
 };
 use glam::f32::Vec2;
 
 const SCREEN: (f32, f32) = (DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT);
 const DELTA_TIME: f32 = 1. / 60.;
 
+fn bestiary() -> Bestiary { Bestiary::from_dir("resources/enemies").unwrap() }
+
+/// An empty room around the default player that enemies act in, it keeps the shots they fire.
+///
+struct Arena {
+    player: Player,
+    obstacles: Vec<Box<dyn Stationary>>,
+    flow: FlowField,
+    allies: AllyGrid,
+    shots: Vec<Shot>,
+}
+
+impl Arena {
+    fn new() -> Self {
+        Self {
+            player: Player::default(),
+            obstacles: Vec::new(),
+            flow: FlowField::default(),
+            allies: AllyGrid::default(),
+            shots: Vec::new(),
+        }
+    }
+
+    fn act(&mut self, actor: &mut dyn Actor) {
+        let act = ActContext { sw: SCREEN.0, sh: SCREEN.1, flow: &self.flow, obstacles: &self.obstacles, allies: &self.allies, player: &self.player };
+        actor.act(&act, &mut self.shots).unwrap();
+    }
+}
+
+/// Spawns the enemy for `c` on the first floor, ready to act right away.
+///
+fn spawn_ready(c: char, pos: Vec2) -> Box<dyn Actor> {
+    let mut enemy = Room::spawn_enemy(c, pos, &Difficulty::for_level(1), &bestiary()).unwrap();
+    enemy.as_any_mut().downcast_mut::<Enemy>().unwrap().afterlock_cooldown = 0.;
+    enemy
+}
+
 #[test]
 fn test_player_shoot() {
     let mut player = Player::default();
@@ -32,13 +76,13 @@ fn test_player_shoot() {
 #[test]
 fn test_enemy_shooter() {
     let player = Player::default();
-    let mut enemy = EnemyMask {
+    let mut enemy = Enemy {
         props: ActorProps {
             pos: Vec2::new(player.props.pos.0.x - ENEMY_SHOOT_RANGE / 2., player.props.pos.0.y).into(),
             ..Default::default()
         },
         afterlock_cooldown: 0.,
-        ..Default::default()
+        ..bestiary().get('m').unwrap().clone()
     };
     let mut shots = Vec::new();
 
@@ -67,6 +111,36 @@ fn test_enemy_shooter() {
     assert_eq!(shots.len(), 1);
 }
 
+#[test]
+fn test_enemy_components() {
+    let mut arena = Arena::new();
+    let player = &arena.player;
+    let mut enemy = Enemy {
+        props: ActorProps {
+            pos: Vec2::new(player.props.pos.0.x - ENEMY_SHOOT_RANGE / 2., player.props.pos.0.y).into(),
+            scale: Vec2::splat(ENEMY_SCALE),
+            ..Default::default()
+        },
+        afterlock_cooldown: 0.,
+        movement: Movement::Chase,
+        attack: Attack::Aimed,
+        ..bestiary().get('b').unwrap().clone()
+    };
+    enemy.brain = None;
+    enemy.accuracy = Accuracy { lead: 0., spread: 0. };
+
+    arena.act(&mut enemy);
+
+    assert_eq!(enemy.get_translation(), Vec2::X);
+    assert_eq!(arena.shots.len(), 1);
+    assert_eq!(arena.shots[0].get_translation(), Vec2::X);
+
+    enemy.on_hit = OnHit::Ignore;
+    enemy.damage(1.);
+
+    assert_eq!(enemy.get_health(), bestiary().get('b').unwrap().health);
+}
+
 #[test]
 fn test_velocity_lerp() {
     let mut player = Player::default();
@@ -86,3 +160,549 @@ fn test_velocity_lerp() {
 
     assert_eq!(player.get_velocity().length(), 0.);
 }
+
+#[test]
+fn test_creep() {
+    let mut player = Player::default();
+
+    assert!(player.leave_creep().is_none());
+
+    player.puke_trail = true;
+    player.props.velocity = Vec2::X;
+    let mut creep = vec![player.leave_creep().unwrap()];
+
+    assert_eq!(creep[0].tag, CreepTag::Player);
+    assert_eq!(player.creep_cooldown, CREEP_COOLDOWN);
+    assert!(player.leave_creep().is_none());
+
+    let pos = player.get_pos();
+    assert_eq!(Creep::effect_at(&creep, pos, CreepTag::Player, SCREEN.0, SCREEN.1), Some((CREEP_PUKE_DAMAGE, CREEP_PUKE_SLOW)));
+    assert!(Creep::effect_at(&creep, pos, CreepTag::Enemy, SCREEN.0, SCREEN.1).is_none());
+    assert!(Creep::effect_at(&creep, pos + Vec2::X * SCREEN.0, CreepTag::Player, SCREEN.0, SCREEN.1).is_none());
+
+    creep[0].update(CREEP_LIFETIME / 2.);
+    assert!(!creep[0].is_dry());
+    creep[0].update(CREEP_LIFETIME);
+    assert!(creep[0].is_dry());
+}
+
+#[test]
+fn test_surface_velocity_lerp() {
+    let mut player = Player::default();
+    let mut player_ice = Player::default();
+    player_ice.set_surface(Surface::Ice);
+    player.props.translation = Vec2::X;
+    player_ice.props.translation = Vec2::X;
+
+    player.velocity_lerp(DELTA_TIME, player.speed, DELTA_TIME * 2., 400.);
+    player_ice.velocity_lerp(DELTA_TIME, player_ice.speed, DELTA_TIME * 2., 400.);
+
+    assert!(player_ice.get_velocity().length() < player.get_velocity().length());
+
+    let mut player_sticky = player;
+    player_sticky.set_surface(Surface::Sticky);
+    player.props.velocity = Vec2::X * 100.;
+    player_sticky.props.velocity = Vec2::X * 100.;
+
+    player.velocity_lerp(DELTA_TIME, player.speed, DELTA_TIME * 2., 400.);
+    player_sticky.velocity_lerp(DELTA_TIME, player_sticky.speed, DELTA_TIME * 2., 400.);
+
+    assert_eq!(player.get_velocity().length(), player.speed);
+    assert_eq!(player_sticky.get_velocity().length(), player.speed * SURFACE_STICKY_MODIFIERS.0);
+
+    let mut shots = Vec::new();
+    player.shoot(&mut shots);
+    shots[0].set_surface(Surface::Ice);
+    assert_eq!(shots[0].get_surface(), Surface::Ice);
+}
+
+#[test]
+fn test_ai_state_machine() {
+    let player = Player::default();
+    let target = player.get_pos();
+    let pos = target - Vec2::X * 300.;
+    let stone: Vec<Box<dyn Stationary>> = vec![
+        Box::new(Block {
+            pos: (pos + Vec2::X * 150.).into(),
+            scale: Vec2::splat(WALL_SCALE),
+            tag: BlockTag::Stone,
+        }),
+    ];
+    let mut brain = Brain::new(AI_BLUE_GUY);
+
+    assert!(has_line_of_sight(pos, target, &Vec::new(), SCREEN.0, SCREEN.1));
+    assert!(!has_line_of_sight(pos, target, &stone, SCREEN.0, SCREEN.1));
+
+    assert_eq!(brain.think(pos, target, &stone, SCREEN.0, SCREEN.1), AiState::Idle);
+    assert_eq!(brain.think(pos, target, &Vec::new(), SCREEN.0, SCREEN.1), AiState::Alert);
+    assert_eq!(brain.think(pos, target, &Vec::new(), SCREEN.0, SCREEN.1), AiState::Alert);
+
+    brain.tick(AI_BLUE_GUY.alert_time);
+    assert_eq!(brain.think(pos, target, &Vec::new(), SCREEN.0, SCREEN.1), AiState::Pursue);
+    assert_eq!(brain.destination(pos, target), Some(target));
+
+    let hidden = target + Vec2::Y * 10.;
+    assert_eq!(brain.think(pos, hidden, &stone, SCREEN.0, SCREEN.1), AiState::Search);
+    assert_eq!(brain.destination(pos, hidden), Some(target));
+
+    brain.tick(AI_BLUE_GUY.search_time);
+    assert_eq!(brain.think(pos, hidden, &stone, SCREEN.0, SCREEN.1), AiState::Idle);
+    assert_eq!(brain.destination(pos, hidden), None);
+
+    let mut brain = Brain { state: AiState::Attack, ..Brain::new(AI_SLIME) };
+    assert_eq!(brain.think(target - Vec2::X * AI_SLIME.retreat_range / 2., target, &Vec::new(), SCREEN.0, SCREEN.1), AiState::Retreat);
+}
+
+#[test]
+fn test_evasion() {
+    let pos = Vec2::new(SCREEN.0, SCREEN.1) / 2.;
+    let radius = 30.;
+    let shot = |from: Vec2, dir: Vec2, tag: ShotTag| Shot {
+        props: ActorProps {
+            pos: from.into(),
+            scale: Vec2::splat(SHOT_SCALE),
+            translation: dir,
+            ..Default::default()
+        },
+        spawn_pos: from.into(),
+        speed: SHOT_SPEED,
+        range: 1000.,
+        damage: 1.,
+        tag,
+    };
+
+    let incoming = shot(pos - Vec2::X * 100. + Vec2::Y * 5., Vec2::X, ShotTag::Player);
+    let dir = Evasion::threat(pos, radius, &[incoming], 1., SCREEN.0, SCREEN.1).unwrap();
+    assert!(dir.dot(-Vec2::Y) > 0.99);
+
+    let passing = shot(pos - Vec2::X * 100. + Vec2::Y * 200., Vec2::X, ShotTag::Player);
+    let leaving = shot(pos + Vec2::X * 100., Vec2::X, ShotTag::Player);
+    let hostile = shot(pos - Vec2::X * 100., Vec2::X, ShotTag::Enemy);
+    let far = shot(pos - Vec2::X * 1000., Vec2::X, ShotTag::Player);
+    assert!(Evasion::threat(pos, radius, &[passing, leaving, hostile, far], 1., SCREEN.0, SCREEN.1).is_none());
+
+    let mut evasion = Evasion::new(EvasionConfig { agility: 1., recharge: 1., look_ahead: 1., duration: 0.5 });
+    assert_eq!(evasion.dodge(pos, radius, &[incoming], SCREEN.0, SCREEN.1), Some(dir));
+    assert!(evasion.is_dodging());
+    assert_eq!(evasion.dodge(pos, radius, &[], SCREEN.0, SCREEN.1), Some(dir));
+
+    evasion.tick(0.5);
+    assert!(!evasion.is_dodging());
+    assert_eq!(evasion.dodge(pos, radius, &[incoming], SCREEN.0, SCREEN.1), None);
+
+    evasion.tick(0.5);
+    assert!(evasion.dodge(pos, radius, &[incoming], SCREEN.0, SCREEN.1).is_some());
+}
+
+#[test]
+fn test_predictive_aiming() {
+    let shooter = Vec2::ZERO;
+    let target = Vec2::new(300., 0.);
+    let velocity = Vec2::new(0., 3.);
+
+    let point = intercept(shooter, target, velocity, SHOT_SPEED).unwrap();
+    let t = point.distance(shooter) / SHOT_SPEED;
+    assert!(point.distance(target + velocity * t) < 1e-2);
+    assert_eq!(intercept(shooter, target, Vec2::X * SHOT_SPEED * 2., SHOT_SPEED), None);
+    assert_eq!(intercept(shooter, target, Vec2::ZERO, SHOT_SPEED), Some(target));
+
+    let perfect = aim(shooter, target, velocity, SHOT_SPEED, &Accuracy { lead: 1., spread: 0. });
+    let naive = aim(shooter, target, velocity, SHOT_SPEED, &Accuracy { lead: 0., spread: 0. });
+    assert!(perfect.abs_diff_eq(point.normalize(), 1e-5));
+    assert_eq!(naive, Vec2::X);
+
+    let spread = 0.2;
+    for _ in 0..20 {
+        let dir = aim(shooter, target, Vec2::ZERO, SHOT_SPEED, &Accuracy { lead: 1., spread });
+        assert!(dir.angle_between(Vec2::X).abs() <= spread + 1e-5);
+    }
+
+    let mask = bestiary().get('m').unwrap().accuracy;
+    let first = mask.scaled(&Difficulty::for_level(1).enemy_accuracy);
+    let last = mask.scaled(&Difficulty::for_level(MAX_LEVEL).enemy_accuracy);
+    assert!(last.lead > first.lead);
+    assert!(last.spread < first.spread);
+}
+
+#[test]
+fn test_flocking() {
+    let pos = Vec2::new(SCREEN.0, SCREEN.1) / 2.;
+    let ally = |offset: Vec2, flocks: bool| Ally { pos: pos + offset, velocity: Vec2::Y, flocks };
+    let allies = AllyGrid::from_allies(vec![
+        ally(Vec2::ZERO, true),
+        ally(Vec2::X * 20., true),
+        ally(Vec2::X * 100., false),
+        ally(Vec2::X * 400., true),
+    ], SCREEN.0, SCREEN.1);
+
+    let nearby = allies.nearby(pos, FLOCK_FLY.radius);
+    assert_eq!(allies.len(), 4);
+    assert_eq!(nearby.len(), 2);
+    assert!(nearby.iter().all(|a| a.pos != pos));
+
+    // Crowded from the right, the fly is pushed away from its closest neighbour.
+    let steer = flock(pos, &nearby[..1], Vec2::ZERO, &FlockConfig { cohesion: 0., alignment: 0., ..FLOCK_FLY });
+    assert!(steer.x < 0.);
+
+    // Alone, it only follows the attraction.
+    assert_eq!(flock(pos, &[], Vec2::X * 50., &FLOCK_FLY), Vec2::X);
+
+    // Far from the swarm, cohesion and alignment pull it along with the others.
+    let far = [Ally { pos: pos + Vec2::X * 110., velocity: Vec2::Y, flocks: true }];
+    let steer = flock(pos, &far, Vec2::ZERO, &FLOCK_FLY);
+    assert!(steer.x > 0. && steer.y > 0.);
+    assert!(steer.length() <= 1.);
+}
+
+#[test]
+fn test_enemy_definitions() {
+    let bestiary = bestiary();
+    for c in ['m', 'b', 's', 'y', 'B'] {
+        assert!(bestiary.contains(c));
+    }
+    assert!(bestiary.get('B').unwrap().is_boss);
+    assert_eq!(bestiary.get('s').unwrap().props.scale, Vec2::new(0.8, 0.4));
+
+    let (c, enemy) = Bestiary::parse("char = k\nlook = rotating\nsprite = enemy_blue_guy_base\n\n# wandering shooter\nmovement = wander\nattack = aimed\nhealth = 7").unwrap();
+    assert_eq!(c, 'k');
+    assert_eq!(enemy.movement, Movement::Wander);
+    assert_eq!(enemy.attack, Attack::Aimed);
+    assert_eq!(enemy.max_health, 7.);
+
+    assert_eq!(Bestiary::parse("char = k\nhelth = 3").unwrap_err(), "line 2: unknown key `helth`");
+    assert_eq!(Bestiary::parse("char = k\nspeed = fast").unwrap_err(), "line 2: `speed` expects a number, found `fast`");
+    assert!(Bestiary::parse("char = #").unwrap_err().contains("reserved"));
+    assert!(Bestiary::parse("char = k").unwrap_err().contains("missing `look`"));
+    assert_eq!(Bestiary::parse("char = k\nshoot_rate = 0").unwrap_err(), "line 2: `shoot_rate` must be greater than 0, found `0`");
+    assert_eq!(Bestiary::parse("char = k\nspeed = -1").unwrap_err(), "line 2: `speed` can't be negative, found `-1`");
+    assert!(Bestiary::parse("char = k\nhealth = 0").is_err());
+    assert!(Bestiary::parse("char = k\ndamage = NaN").is_err());
+    assert!(Bestiary::parse("char = k\nshoot_range = inf").is_err());
+    assert!(Bestiary::parse("char = k\nscale = 1 0").is_err());
+    assert!(Bestiary::parse("char = k\nvolley = 0 1 0").is_err());
+
+    let source = "char = k\nlook = fly".to_string();
+    assert!(Bestiary::from_sources(&[("a.txt".to_string(), source.clone()), ("b.txt".to_string(), source)]).is_err());
+}
+
+#[test]
+fn test_charger() {
+    let player = Player::default();
+    let (sw, sh) = SCREEN;
+    let tile = sw / ROOM_WIDTH as f32;
+    let config = bestiary().get('c').map(|c| c.movement);
+    let mut charge = match config {
+        Some(Movement::Charge(charge)) => charge,
+        _ => panic!("charger must charge"),
+    };
+    let pos = player.get_pos() - Vec2::X * tile * 4.;
+    let radius = tile * 0.4;
+    let open: Vec<Box<dyn Stationary>> = Vec::new();
+
+    assert_eq!(charge.charge(pos + Vec2::Y * tile * 2., player.get_pos(), radius, &open, sw, sh), None);
+    assert_eq!(charge.charge(pos, player.get_pos(), radius, &open, sw, sh), Some(Vec2::ZERO));
+    assert_eq!(charge.dir(), Some(Vec2::X));
+
+    let blocked: Vec<Box<dyn Stationary>> = vec![
+        Box::new(Block {
+            pos: (pos + Vec2::X * tile * 2.).into(),
+            scale: Vec2::splat(WALL_SCALE),
+            tag: BlockTag::Stone,
+        }),
+    ];
+    assert!(Charge::lined_up(pos, player.get_pos(), charge.config.tolerance, &blocked, sw, sh).is_none());
+
+    charge.tick(charge.config.windup);
+    assert_eq!(charge.charge(pos, player.get_pos(), radius, &open, sw, sh), Some(Vec2::X));
+    assert!(charge.is_dashing());
+
+    let wall: Vec<Box<dyn Stationary>> = vec![
+        Box::new(Block {
+            pos: (pos + Vec2::X * (radius + tile * 0.5)).into(),
+            scale: Vec2::splat(WALL_SCALE),
+            tag: BlockTag::Wall,
+        }),
+    ];
+    assert_eq!(charge.charge(pos, player.get_pos(), radius, &wall, sw, sh), Some(Vec2::ZERO));
+    assert!(charge.is_stunned());
+
+    charge.tick(charge.config.stun);
+    assert_eq!(charge.charge(pos, player.get_pos(), radius, &wall, sw, sh), None);
+    assert_eq!(charge.phase, ChargePhase::Patrol);
+
+    let (_, tuned) = Bestiary::parse("char = k\nlook = charger\ncharge = 0.2 5 1 0.5").unwrap();
+    assert!(matches!(tuned.movement, Movement::Charge(c) if c.config.dash_speed == 5.));
+    assert!(Bestiary::parse("char = k\nlook = charger\ncharge = 0.2 0 1 0.5").is_err());
+}
+
+#[test]
+fn test_enemy_split() {
+    let (sw, sh) = SCREEN;
+    let pos = Vec2::new(sw, sh) / 2. + Vec2::X * sw / ROOM_WIDTH as f32 * 0.45;
+    let mut generation: Vec<Box<dyn Actor>> = vec![Room::spawn_enemy('S', pos, &Difficulty::for_level(1), &bestiary()).unwrap()];
+    let mut sizes = Vec::new();
+    let (i, j) = pos_to_room_coords(pos, sw, sh);
+    let mut grid = [[i32::MIN; ROOM_WIDTH]; ROOM_HEIGHT];
+    grid[i][j] = 0;
+
+    while !generation.is_empty() {
+        sizes.push(generation.len());
+        let parent = (generation[0].get_scale(), generation[0].get_health());
+
+        generation = generation.iter_mut().flat_map(|e| e.on_death(&grid, sw, sh)).collect();
+
+        for child in generation.iter() {
+            assert!(child.get_scale().x < parent.0.x);
+            assert!(child.get_health() < parent.1);
+            assert_eq!(child.get_state(), ActorState::Base);
+            assert!(child.get_pos().distance(pos) < sw / ROOM_WIDTH as f32 * 2.);
+            assert_eq!(pos_to_room_coords(child.get_pos(), sw, sh), (i, j));
+        }
+    }
+
+    assert_eq!(sizes, vec![1, 2, 4]);
+    assert!(bestiary().get('s').unwrap().clone().on_death(&grid, sw, sh).is_empty());
+    assert!(Bestiary::parse("char = k\nlook = fly\nsplit = 2 1 1 1 0.4").unwrap_err().contains("below 1"));
+}
+
+#[test]
+fn test_turrets() {
+    let player = Player::default();
+    let (sw, sh) = SCREEN;
+    let tile = f32::min(sw / ROOM_WIDTH as f32, sh / ROOM_HEIGHT as f32);
+    let no_obstacles: Vec<Box<dyn Stationary>> = Vec::new();
+
+    let wall_pos = room_coords_to_pos(ROOM_HEIGHT / 2, 0, sw, sh);
+    let mut wall_turret = Room::spawn_enemy('T', wall_pos, &Difficulty::for_level(1), &bestiary()).unwrap();
+    let mut shots = Vec::new();
+
+    assert!(!wall_turret.blocks_clear());
+    wall_turret.damage(100.);
+    assert!(wall_turret.get_health() > 0.);
+
+    let turret = wall_turret.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    turret.afterlock_cooldown = 0.;
+    turret.shoot(sw, sh, &no_obstacles, &mut shots, &player);
+
+    assert_eq!(shots.len(), 3);
+    assert!(shots.iter().all(|s| s.get_translation().x > 0. && s.get_pos().distance(wall_pos) >= tile * 0.99));
+
+    turret.shoot_timeout = 0.;
+    turret.shut_off();
+    turret.shoot(sw, sh, &no_obstacles, &mut shots, &player);
+    assert_eq!(shots.len(), 3);
+
+    let mut ring = Room::spawn_enemy('t', player.get_pos(), &Difficulty::for_level(1), &bestiary()).unwrap();
+    let ring = ring.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    ring.afterlock_cooldown = 0.;
+    ring.props.forward = Vec2::X;
+    shots.clear();
+    ring.shoot(sw, sh, &no_obstacles, &mut shots, &player);
+
+    assert!(ring.blocks_clear());
+    assert_eq!(shots.len(), 8);
+    for (a, b) in shots.iter().zip(shots.iter().skip(1)) {
+        assert!((a.get_translation().angle_between(b.get_translation()) - std::f32::consts::TAU / 8.).abs() < 1e-4);
+    }
+    assert!((Vec2::X.angle_between(ring.get_forward()) - 0.2).abs() < 1e-4);
+
+    assert!(ring.anchored);
+    assert_eq!(ring.get_pos(), player.get_pos());
+    let mut bumped = Player::default();
+    bumped.set_pos(ring.get_pos() + Vec2::X * 5.);
+    resolve_anchored_collision(ring, &mut bumped, sw, sh);
+    assert_eq!(ring.get_pos(), player.get_pos());
+    assert!((bumped.get_pos().distance(ring.get_pos()) - ring.get_bcircle(sw, sh).1 - bumped.get_bcircle(sw, sh).1).abs() < 0.01);
+
+    assert!(Bestiary::parse("char = k\nlook = turret\non_hit = ignore").unwrap_err().contains("blocks_clear"));
+}
+
+#[test]
+fn test_burrower() {
+    let mut arena = Arena::new();
+    let mut burrower = spawn_ready('w', arena.player.get_pos() - Vec2::X * 200.);
+
+    let enemy = burrower.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    let health = enemy.get_health();
+    let config = match enemy.movement {
+        Movement::Burrow(burrow) => burrow.config,
+        _ => panic!("burrower must burrow"),
+    };
+
+    assert!(!enemy.is_hittable());
+    enemy.damage(1.);
+    arena.act(enemy);
+    assert_eq!(enemy.get_health(), health);
+    assert_eq!(enemy.get_translation(), Vec2::X);
+    assert!(arena.shots.is_empty());
+
+    let mut burrow = Burrow::new(config);
+    burrow.tick(config.underground);
+    assert_eq!(burrow.phase, BurrowPhase::Surfacing);
+    burrow.tick(config.telegraph);
+    assert!(burrow.is_surfaced());
+
+    enemy.movement = Movement::Burrow(burrow);
+    arena.act(enemy);
+    assert!(enemy.is_hittable());
+    assert_eq!(enemy.get_translation(), Vec2::ZERO);
+    assert!(!arena.shots.is_empty());
+
+    enemy.damage(1.);
+    assert!(enemy.get_health() < health);
+
+    burrow.tick(config.surfaced);
+    assert!(burrow.is_underground());
+
+    assert!(Bestiary::parse("char = k\nlook = burrower\nburrow = 0 1 1").is_err());
+}
+
+#[test]
+fn test_spawner() {
+    let mut arena = Arena::new();
+    let (sw, sh) = SCREEN;
+    let pos = arena.player.get_pos() - Vec2::X * 200.;
+    let mut nest = spawn_ready('n', pos);
+    let grid = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
+    let mut minions = Vec::new();
+
+    let enemy = nest.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    let config = enemy.spawner.as_ref().unwrap().config;
+    assert!(config.minions_die);
+
+    arena.act(enemy);
+    assert!(enemy.take_spawns(sw, sh).is_empty());
+
+    for _ in 0..config.cap + 2 {
+        enemy.spawner.as_mut().unwrap().tick(config.interval);
+        arena.act(enemy);
+        minions.extend(enemy.take_spawns(sw, sh));
+    }
+    assert_eq!(minions.len(), config.cap);
+    assert!(minions.iter().all(|m| m.get_pos().distance(pos) <= enemy.get_bcircle(sw, sh).1 + 0.01));
+
+    let copy = minions[0].as_any().downcast_ref::<Enemy>().unwrap().clone();
+    minions.pop().unwrap().on_death(&grid, sw, sh);
+    assert_eq!(enemy.spawner.as_ref().unwrap().brood.size(), config.cap - 1);
+    drop(copy);
+    enemy.spawner.as_mut().unwrap().tick(config.interval);
+    arena.act(enemy);
+    minions.extend(enemy.take_spawns(sw, sh));
+    assert_eq!(minions.len(), config.cap);
+
+    enemy.spawner.as_mut().unwrap().pending = 1;
+    enemy.state = ActorState::Dead;
+    assert!(enemy.take_spawns(sw, sh).is_empty());
+    enemy.on_death(&grid, sw, sh);
+    for minion in minions.iter_mut() {
+        arena.act(minion.as_mut());
+        assert!(minion.get_health() <= 0.);
+    }
+
+    let mut loyal = spawn_ready('n', pos);
+    let enemy = loyal.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    let spawner = enemy.spawner.as_mut().unwrap();
+    spawner.config.minions_die = false;
+    spawner.timer = 0.;
+    arena.act(enemy);
+    let mut minion = enemy.take_spawns(sw, sh).pop().unwrap();
+    enemy.on_death(&grid, sw, sh);
+    arena.act(minion.as_mut());
+    assert!(minion.get_health() > 0.);
+
+    let source = "char = k\nlook = nest\nminion = q".to_string();
+    assert!(Bestiary::from_sources(&[("k.txt".to_string(), source)]).unwrap_err().to_string().contains("unknown minion"));
+    assert!(Bestiary::parse("char = k\nlook = nest\nspawn = 2 3 true").unwrap_err().contains("`minion`"));
+}
+
+#[test]
+fn test_worm() {
+    let mut arena = Arena::new();
+    let (sw, sh) = SCREEN;
+    let pos = arena.player.get_pos() - Vec2::X * 300.;
+    let (i, j) = pos_to_room_coords(pos, sw, sh);
+    let mut grid = [[0; ROOM_WIDTH]; ROOM_HEIGHT];
+    grid[i][j + 1] = -1;
+    let mut worm = Room::spawn_enemies('W', pos, &Difficulty::for_level(1), &bestiary(), &grid, sw, sh);
+    let mut body = worm.split_off(1);
+    let mut worm = worm.remove(0);
+
+    let config = worm.as_any().downcast_ref::<Enemy>().unwrap().worm.unwrap();
+    assert_eq!(body.len(), config.segments - 1);
+    assert!(worm.as_any_mut().downcast_mut::<Enemy>().unwrap().grow_body(&grid, sw, sh).is_empty());
+    assert!(body.iter().all(|s| s.draw_layer() < worm.draw_layer()));
+    assert!(body.iter().all(|s| pos_to_room_coords(s.get_pos(), sw, sh) != (i, j + 1)));
+
+    let gap = worm.get_bcircle(sw, sh).1 * config.spacing;
+    let links = std::iter::once(&worm).chain(body.iter()).map(|s| s.get_pos()).collect::<Vec<_>>();
+    assert!(links.windows(2).all(|w| w[0].distance(w[1]) > 0. && w[0].distance(w[1]) <= gap + 0.01));
+    let head = worm.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    let segment = body[0].as_any().downcast_ref::<Enemy>().unwrap().segment.clone().unwrap();
+    assert!(!segment.is_head());
+    assert!(segment.follow(gap).unwrap().distance(body[0].get_pos()) < 0.01);
+
+    let path = [Vec2::Y, Vec2::Y * 2., Vec2::new(1., 2.), Vec2::new(2., 2.)].map(|p| pos + p * gap * 0.5);
+    for p in path { head.segment.as_ref().unwrap().record(p, gap); }
+    assert!(segment.follow(gap).unwrap().distance(path[1]) < 0.01);
+
+    arena.act(body[0].as_mut());
+    assert_eq!(body[0].get_translation(), Vec2::ZERO);
+    let health = body[1].get_health();
+    body[0].damage(1.);
+    arena.act(body[1].as_mut());
+    assert_eq!(body[1].get_health(), health);
+
+    drop(worm);
+    assert!(segment.is_head());
+    body[0].as_any_mut().downcast_mut::<Enemy>().unwrap().afterlock_cooldown = 0.;
+    arena.act(body[0].as_mut());
+    assert_ne!(body[0].get_translation(), Vec2::ZERO);
+    body.remove(2);
+    assert!(body[2].as_any().downcast_ref::<Enemy>().unwrap().segment.as_ref().unwrap().is_head());
+
+    let (_, mut shared) = Bestiary::parse("char = k\nlook = worm\nhealth = 2\nworm = 3 1 shared").unwrap();
+    let mut body = shared.grow_body(&grid, sw, sh);
+    assert_eq!(shared.get_health(), 6.);
+    body[1].damage(2.);
+    arena.act(&mut shared);
+    arena.act(&mut body[0]);
+    assert_eq!(shared.get_health(), 4.);
+    assert_eq!(body[0].get_health(), 4.);
+    assert!(Bestiary::parse("char = k\nlook = worm\nworm = 3 1 both").is_err());
+}
+
+#[test]
+fn test_orbit() {
+    let (sw, sh) = SCREEN;
+    let pos = Vec2::new(sw, sh) / 2.;
+    let mut warden = Room::spawn_enemy('O', pos, &Difficulty::for_level(1), &bestiary()).unwrap();
+    let radius = warden.get_bcircle(sw, sh).1;
+    let config = warden.as_any().downcast_ref::<Enemy>().unwrap().orbit.as_ref().unwrap().config;
+
+    let shields = warden.get_shields(sw, sh);
+    assert_eq!(shields.len(), config.count);
+    assert!(shields.iter().all(|((p, _), _)| (p.0.distance(pos) - radius * config.distance).abs() < 0.01));
+
+    warden.set_pos(pos + Vec2::X * 100.);
+    let moved = warden.get_shields(sw, sh);
+    assert!(shields.iter().zip(moved.iter()).all(|(a, b)| (b.0.0.0 - a.0.0.0).distance(Vec2::X * 100.) < 0.01));
+
+    let enemy = warden.as_any_mut().downcast_mut::<Enemy>().unwrap();
+    enemy.orbit.as_mut().unwrap().tick(0.5);
+    let turned = enemy.get_shields(sw, sh);
+    let angle = (moved[0].0.0.0 - enemy.get_pos()).angle_between(turned[0].0.0.0 - enemy.get_pos());
+    assert!((angle - config.speed * 0.5).abs() < 0.01);
+    let ((shield, _), spin) = turned[0];
+    assert!(spin.dot(shield.0 - enemy.get_pos()).abs() < 0.01);
+    assert!((spin.length() - radius * config.distance * config.speed.abs()).abs() < 0.01);
+
+    let health = enemy.get_health();
+    enemy.damage_shield(1, config.health * 0.5);
+    assert_eq!(enemy.get_shields(sw, sh).len(), config.count);
+    enemy.damage_shield(1, config.health * 0.5);
+    assert_eq!(enemy.get_shields(sw, sh).len(), config.count - 1);
+    assert_eq!(enemy.get_health(), health);
+
+    assert!(Bestiary::parse("char = k\nlook = warden\norbit = 3 2").is_err());
+    assert!(Bestiary::parse("char = k\nlook = warden\norbit = 2.7 2 1 3").is_err());
+}
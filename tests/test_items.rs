@@ -3,6 +3,8 @@ use puker::{
     consts::*,
     utils::*,
     items::*,
+    dungeon::{RoomState, RoomTag},
+    ui_elements::MapRoom,
 };
 
 #[test]
@@ -77,3 +79,23 @@ fn test_player_use_item() {
     assert_eq!(player.health, 3.);
     assert_eq!(player.item.unwrap().cooldown, ITEM_COOLDOWN);
 }
+
+#[test]
+fn test_player_pick_up_map_items() {
+    let mut player = Player::default();
+    let mut compass = Item { tag: ItemTag::Passive(ItemPassive::Compass), cooldown: 0. };
+    let mut map = Item { tag: ItemTag::Passive(ItemPassive::Map), cooldown: 0. };
+    let boss_room = MapRoom { state: RoomState::Undiscovered, tag: RoomTag::Boss, has_loot: false };
+    let mob_room = MapRoom { state: RoomState::Undiscovered, tag: RoomTag::Mob, has_loot: false };
+
+    assert!(!boss_room.is_icon_visible(player.has_compass));
+
+    compass.affect_player(&mut player);
+    map.affect_player(&mut player);
+
+    assert!(player.has_compass);
+    assert!(player.has_map);
+    assert!(boss_room.is_icon_visible(player.has_compass));
+    assert!(!mob_room.is_icon_visible(player.has_compass));
+    assert!(MapRoom { state: RoomState::Discovered, ..mob_room }.is_icon_visible(false));
+}
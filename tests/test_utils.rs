@@ -75,6 +75,17 @@ fn test_dynamic_circle_vs_circle() {
     assert_eq!(vel2, Vec2::ZERO);
 }
 
+#[test]
+fn test_contact_distance() {
+    let shot = (Vec2::ZERO.into(), 1.);
+    let near = (Vec2::new(2., 1.).into(), 1.);
+    let far = (Vec2::new(2.5, 0.).into(), 1.);
+
+    assert!((contact_distance(&shot, &Vec2::X, &far) - 0.5).abs() < 0.01);
+    assert!(contact_distance(&shot, &Vec2::X, &near) < contact_distance(&shot, &Vec2::X, &far));
+    assert!(contact_distance(&shot, &-Vec2::X, &far) < 0.);
+}
+
 #[test]
 fn test_mouse_to_screen_coords() {
     let (mx1, my1) = (0., 0.);